@@ -1,133 +1,543 @@
-use crate::geometry::Point;
+use std::{
+    cell::OnceCell,
+    ops::{Add, Mul, Sub},
+};
 
-const DERIVATIVE_CTRL_POINTS: usize = 3;
+use crate::{
+    curve::{direction, ArcLengthTable, Curve},
+    geometry::{Affine, Point, Pose, Rect, Transform},
+};
 
+/// Number of samples used to fit a curve of reduced degree
+const DEGREE_REDUCTION_SAMPLES: usize = 32;
+/// Limits the subdivision depth when searching for polynomial roots
+const ROOT_MAX_DEPTH: u32 = 32;
+/// Roots of polynomials are located to within this range of t
+const ROOT_EPSILON: f64 = 1e-6;
+/// Limits the subdivision depth when flattening a curve
+pub(crate) const FLATTEN_MAX_DEPTH: u32 = 16;
+
+/// A bezier curve of any degree. The degree is one less than the number of
+/// control points, so straight track is a degree 1 curve with 2 control
+/// points and flex track is usually a cubic with 4.
 #[derive(Debug, Clone)]
 pub struct Bezier {
-    ctrl_point: [Point; 4],
-    resolution: f32,
-    length: f32,
-    modified: bool,
-    curve: Option<Vec<Point>>,
+    ctrl_point: Vec<Point>,
+    tolerance: f64,
+    /// Vertices of the curve flattened to the tolerance on first use and
+    /// cleared whenever the curve changes
+    curve: OnceCell<Vec<Point>>,
+    /// Arc length table built to the tolerance on first use and cleared
+    /// whenever the curve changes
+    table: OnceCell<ArcLengthTable>,
 }
 
 impl Bezier {
-    pub fn new(resolution: f32) -> Self {
+    /// Creates a new cubic bezier curve with all control points at the origin
+    pub fn new(tolerance: f64) -> Self {
         Bezier {
-            ctrl_point: [
-                Point { x: 0.0, y: 0.0 },
-                Point { x: 0.0, y: 0.0 },
-                Point { x: 0.0, y: 0.0 },
-                Point { x: 0.0, y: 0.0 },
-            ],
-            resolution,
-            length: 0.0,
-            modified: true,
-            curve: None,
+            ctrl_point: vec![Point { x: 0.0, y: 0.0 }; 4],
+            tolerance,
+            curve: OnceCell::new(),
+            table: OnceCell::new(),
         }
     }
     /// Creates a new bezier curve with the specified parameters
-    /// resolution - a value representing the resolution of the curve.
-    /// 0.0 < value <= 1.0. A smaller value is a higher resolution
-    pub fn new_with_ctrl_point(ctrl_point: [Point; 4], resolution: f32) -> Self {
-        let mut bezier = Bezier {
+    /// tolerance - the maximum distance in layout units between the curve
+    /// and the line segments used to render and measure it. A smaller value
+    /// gives more line segments.
+    ///
+    /// The degree of the curve is one less than the number of control points.
+    /// Panics if there are fewer than 2 control points.
+    pub fn new_with_ctrl_point(ctrl_point: impl Into<Vec<Point>>, tolerance: f64) -> Self {
+        let ctrl_point = ctrl_point.into();
+        assert!(
+            ctrl_point.len() >= 2,
+            "a bezier curve needs at least 2 control points"
+        );
+        Bezier {
             ctrl_point,
-            resolution,
-            length: 0.0,
-            modified: true,
-            curve: None,
-        };
-        // calculate the curve based on the control points
-        bezier.calc_curve();
-        bezier
+            tolerance,
+            curve: OnceCell::new(),
+            table: OnceCell::new(),
+        }
     }
 
-    pub fn control_points(&self) -> &[Point; 4] {
+    /// Creates the cubic from the start pose to the end pose whose handles
+    /// lie along the headings with the lengths a and b
+    ///      P1 = P0 + a * D0,  P2 = P3 - b * D3
+    pub(crate) fn between_poses(start: &Pose, end: &Pose, a: f64, b: f64, tolerance: f64) -> Self {
+        Bezier::new_with_ctrl_point(
+            [
+                start.point,
+                start.point + start.direction() * a,
+                end.point - end.direction() * b,
+                end.point,
+            ],
+            tolerance,
+        )
+    }
+
+    pub fn control_points(&self) -> &[Point] {
         &self.ctrl_point
     }
 
-    pub fn len(&mut self) -> f32 {
-        if self.modified {
-            self.calc_curve();
+    /// Moves the control point at index to p
+    pub fn set_control_point(&mut self, index: usize, p: Point) {
+        self.ctrl_point[index] = p;
+        self.invalidate();
+    }
+
+    /// Gets the degree of the curve: 1 for a line, 2 for a quadratic and 3
+    /// for a cubic.
+    pub fn degree(&self) -> usize {
+        self.ctrl_point.len() - 1
+    }
+
+    /// Gets the derivative of the specified order of the curve at parameter
+    /// t. The derivative of order k of a curve of degree n is the curve of
+    /// degree n - k with the control points:
+    ///      Q(i) = n! / (n - k)! * D(k, i)
+    /// where D(k, i) is the k-th forward difference of the control points
+    /// starting at P(i).
+    pub fn nth_derivative_at(&self, order: usize, t: f64) -> Point {
+        bernstein_derivative(&self.ctrl_point, order, t.clamp(0.0, 1.0))
+    }
+
+    /// Gets the tight axis aligned bounding box of the curve.
+    ///
+    /// The extremes of the curve on each axis are at the end points or where
+    /// the derivative on that axis is zero. The roots of each axis of the
+    /// derivative are found from its Bernstein coefficients, the differences
+    /// of the control points.
+    pub fn bounds(&self) -> Rect {
+        let differences: Vec<Point> = self.ctrl_point.windows(2).map(|w| w[1] - w[0]).collect();
+        let mut params = vec![0.0, 1.0];
+        params.extend(bernstein_roots(
+            &differences.iter().map(|d| d.x).collect::<Vec<f64>>(),
+        ));
+        params.extend(bernstein_roots(
+            &differences.iter().map(|d| d.y).collect::<Vec<f64>>(),
+        ));
+        let points: Vec<Point> = params.into_iter().map(|t| self.point_at(t)).collect();
+        Rect::from_points(points.iter()).unwrap()
+    }
+
+    /// Gets the curve of one degree higher with exactly the same shape.
+    ///
+    ///      Q(i) = i / (n + 1) * P(i - 1) + (1 - i / (n + 1)) * P(i)
+    pub fn elevate_degree(&self) -> Bezier {
+        let p = &self.ctrl_point;
+        let n = p.len();
+        let mut q = Vec::with_capacity(n + 1);
+        q.push(p[0]);
+        for i in 1..n {
+            let a = i as f64 / n as f64;
+            q.push(p[i - 1] * a + p[i] * (1.0 - a));
         }
-        let mut len = 0.0;
-        let mut prev = &self.curve.as_ref().unwrap()[0];
-        for p in self.curve.as_ref().unwrap().iter().skip(1) {
-            len += prev.distance(p);
-            prev = p;
+        q.push(p[n - 1]);
+        Bezier::new_with_ctrl_point(q, self.tolerance)
+    }
+
+    /// Gets the curve of one degree lower that is the least squares fit of
+    /// this curve, or None for a curve of degree 1.
+    ///
+    /// The end points are kept and the inner control points are chosen to
+    /// minimize the squared distance between the curves at evenly spaced
+    /// values of t. A curve that was created by degree elevation is reduced
+    /// back to exactly the original curve.
+    pub fn reduce_degree(&self) -> Option<Bezier> {
+        let n = self.degree();
+        if n < 2 {
+            return None;
         }
-        self.length = len;
-        self.length
+        let m = n - 1;
+        let start = self.ctrl_point[0];
+        let end = self.ctrl_point[n];
+        if m == 1 {
+            return Some(Bezier::new_with_ctrl_point([start, end], self.tolerance));
+        }
+        // normal equations A^T A x = A^T b for the m - 1 inner points
+        let unknowns = m - 1;
+        let mut matrix = vec![vec![0.0; unknowns]; unknowns];
+        let mut rhs = vec![Point { x: 0.0, y: 0.0 }; unknowns];
+        for k in 0..=DEGREE_REDUCTION_SAMPLES {
+            let t = k as f64 / DEGREE_REDUCTION_SAMPLES as f64;
+            let basis: Vec<f64> = (0..=m).map(|i| bernstein_basis(m, i, t)).collect();
+            let target = self.point_at(t) - start * basis[0] - end * basis[m];
+            for i in 0..unknowns {
+                for j in 0..unknowns {
+                    matrix[i][j] += basis[i + 1] * basis[j + 1];
+                }
+                rhs[i] = rhs[i] + target * basis[i + 1];
+            }
+        }
+        let inner = solve_linear(matrix, rhs)?;
+        let mut q = Vec::with_capacity(m + 1);
+        q.push(start);
+        q.extend(inner);
+        q.push(end);
+        Some(Bezier::new_with_ctrl_point(q, self.tolerance))
+    }
+
+    /// Splits the curve at parameter t into two curves that together have
+    /// exactly the shape of this curve, using de Casteljau's algorithm. Both
+    /// curves keep the tolerance of this curve.
+    pub fn split(&self, t: f64) -> (Bezier, Bezier) {
+        let (left, right) = split_ctrl_points(&self.ctrl_point, t.clamp(0.0, 1.0));
+        (
+            Bezier::new_with_ctrl_point(left, self.tolerance),
+            Bezier::new_with_ctrl_point(right, self.tolerance),
+        )
     }
 
-    pub fn set_resolution(&mut self, resolution: f32) {
-        if self.resolution != resolution {
-            self.resolution = resolution;
-            self.modified = true;
+    /// Gets the part of the curve between the parameters t0 and t1. When t0
+    /// is greater than t1 the part is reversed.
+    pub fn subcurve(&self, t0: f64, t1: f64) -> Bezier {
+        let (t0, t1) = (t0.clamp(0.0, 1.0), t1.clamp(0.0, 1.0));
+        if t0 > t1 {
+            let mut part = self.subcurve(t1, t0);
+            part.ctrl_point.reverse();
+            part.invalidate();
+            return part;
         }
+        let (_, right) = self.split(t0);
+        if t0 >= 1.0 {
+            return right;
+        }
+        // re-map t1 onto the parameter range of the right hand part
+        let (part, _) = right.split((t1 - t0) / (1.0 - t0));
+        part
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: f64) {
+        if self.tolerance != tolerance {
+            self.tolerance = tolerance;
+            self.invalidate();
+        }
+    }
+
+    /// Gets the vertices of the curve flattened to the tolerance. The
+    /// vertices are found once and kept until the curve changes.
+    pub fn curve(&self) -> &[Point] {
+        self.curve.get_or_init(|| {
+            self.flatten()
+                .into_iter()
+                .map(|t| self.point_at(t))
+                .collect()
+        })
+    }
+
+    /// Gets the arc length table of the curve, measured to the tolerance.
+    /// The table is built once and kept until the curve changes.
+    pub fn length_table(&self) -> &ArcLengthTable {
+        self.table
+            .get_or_init(|| self.arc_length_table(self.tolerance))
+    }
+
+    /// Marks the flattened curve and arc length table as out of date
+    fn invalidate(&mut self) {
+        self.curve = OnceCell::new();
+        self.table = OnceCell::new();
+    }
+}
+
+impl Transform for Bezier {
+    /// Transforms the control points. A bezier curve is affine invariant,
+    /// so the transformed control points define the transformed curve.
+    fn transform(&mut self, m: &Affine) {
+        for p in self.ctrl_point.iter_mut() {
+            p.transform(m);
+        }
+        self.invalidate();
+    }
+}
+
+impl Curve for Bezier {
+    /// Gets the point on the curve at parameter t where 0.0 <= t <= 1.0.
+    /// Values of t outside of the range are clamped.
+    fn point_at(&self, t: f64) -> Point {
+        bernstein(self.degree(), t.clamp(0.0, 1.0), |i| self.ctrl_point[i])
+    }
+
+    /// Gets the first derivative of the curve at parameter t. The result is
+    /// the (unnormalized) tangent vector of the curve at t.
+    fn derivative_at(&self, t: f64) -> Point {
+        self.nth_derivative_at(1, t)
+    }
+
+    /// Gets the second derivative of the curve at parameter t.
+    fn second_derivative_at(&self, t: f64) -> Point {
+        self.nth_derivative_at(2, t)
     }
 
-    pub fn translate(&mut self, cx: f32, cy: f32) {
-        if cy != 0. && cy != 0. {
-            for p in self.ctrl_point.as_mut() {
-                p.x += cx;
-                p.y += cy
+    /// Gets the parameters of the vertices of a polyline that stays within
+    /// the curve tolerance of the curve, starting at t = 0.0 and ending at
+    /// t = 1.0.
+    ///
+    /// The curve is recursively split until the inner control points of each
+    /// part are within tolerance of the chord between its end points. As a
+    /// part lies inside the convex hull of its control points, the chord is
+    /// then within tolerance of the part. Tight curves are split more often
+    /// than near straight runs.
+    fn flatten(&self) -> Vec<f64> {
+        let mut params = vec![0.0];
+        flatten_part(&self.ctrl_point, 0.0, 1.0, self.tolerance, 0, &mut params);
+        params
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Gets the vertices of the flattened curve from the cached polyline
+    fn polyline(&self) -> Vec<Point> {
+        self.curve().to_vec()
+    }
+
+    /// Gets the total arc length of the curve from the cached arc length
+    /// table, so that the length agrees with the distance lookups.
+    fn length(&self) -> f64 {
+        self.length_table().length()
+    }
+
+    /// Gets the unit tangent of the curve at parameter t.
+    ///
+    /// Where the first derivative vanishes, for example at an endpoint whose
+    /// control point is coincident with its handle, the direction of the
+    /// higher order derivatives is used since the curve still leaves the
+    /// point in that direction. A curve with all control points coincident
+    /// has no direction and the x axis is returned.
+    fn tangent_at(&self, t: f64) -> Point {
+        let t = t.clamp(0.0, 1.0);
+        if let Some(tangent) = direction(self.derivative_at(t)) {
+            return tangent;
+        }
+        // near a stationary point B'(t + h) ~ h^(k-1) / (k-1)! * B(k)(t) for
+        // the first non zero derivative of order k, so the tangent follows
+        // that derivative. Approaching t = 1 h is negative, which reverses
+        // the direction for even orders.
+        for order in 2..=self.degree() {
+            let sign = if t < 1.0 || order % 2 == 1 { 1.0 } else { -1.0 };
+            if let Some(tangent) = direction(self.nth_derivative_at(order, t) * sign) {
+                return tangent;
             }
-            self.modified = true;
         }
+        let p = &self.ctrl_point;
+        direction(p[p.len() - 1] - p[0]).unwrap_or(Point { x: 1.0, y: 0.0 })
+    }
+}
+
+/// Evaluates the polynomial with the Bernstein coefficients c(0) to c(n) at
+/// t with a Horner like scheme that avoids allocating.
+pub(crate) fn bernstein<T>(n: usize, t: f64, c: impl Fn(usize) -> T) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    if n == 0 {
+        return c(0);
+    }
+    let u = 1.0 - t;
+    let mut binomial = 1.0;
+    let mut tn = 1.0;
+    let mut sum = c(0) * u;
+    for i in 1..n {
+        tn *= t;
+        binomial = binomial * (n - i + 1) as f64 / i as f64;
+        sum = (sum + c(i) * (tn * binomial)) * u;
+    }
+    sum + c(n) * (tn * t)
+}
+
+/// Gets the Bernstein basis polynomial i of degree n at t
+pub(crate) fn bernstein_basis(n: usize, i: usize, t: f64) -> f64 {
+    binomial(n, i) * t.powi(i as i32) * (1.0 - t).powi((n - i) as i32)
+}
+
+pub(crate) fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |c, i| c * (n - i) as f64 / (i + 1) as f64)
+}
+
+/// Gets the derivative of the given order at t of the polynomial with the
+/// Bezier coefficients p.
+pub(crate) fn bernstein_derivative<T>(p: &[T], order: usize, t: f64) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    let n = p.len() - 1;
+    if order > n {
+        return p[0] * 0.0;
+    }
+    let scale = ((n - order + 1)..=n).product::<usize>() as f64;
+    bernstein(n - order, t, |i| forward_difference(&p[i..=i + order])) * scale
+}
+
+/// Gets the forward difference of order p.len() - 1 of the coefficients
+fn forward_difference<T>(p: &[T]) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    let k = p.len() - 1;
+    p[1..]
+        .iter()
+        .enumerate()
+        .fold(p[0] * binomial_sign(k), |sum, (j, q)| {
+            sum + *q * (binomial_sign(k - j - 1) * binomial(k, j + 1))
+        })
+}
+
+/// Gets the sign of the forward difference term with k - j steps
+fn binomial_sign(steps: usize) -> f64 {
+    if steps.is_multiple_of(2) {
+        1.0
+    } else {
+        -1.0
     }
+}
 
-    /// Gets the bezier curve represented as a vector of 2D Points.
-    pub fn curve(&mut self) -> &Vec<Point> {
-        if self.modified {
-            self.calc_curve();
+/// Splits the Bezier coefficients at t using de Casteljau's algorithm. The
+/// coefficients may be control points or the scalar coefficients of a
+/// polynomial in Bernstein form.
+pub(crate) fn split_ctrl_points<T>(p: &[T], t: f64) -> (Vec<T>, Vec<T>)
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T>,
+{
+    let n = p.len();
+    let mut left = Vec::with_capacity(n);
+    let mut right = Vec::with_capacity(n);
+    let mut work = p.to_vec();
+    left.push(work[0]);
+    right.push(work[n - 1]);
+    for level in 1..n {
+        for i in 0..n - level {
+            work[i] = work[i] + (work[i + 1] - work[i]) * t;
         }
-        self.curve.as_ref().unwrap()
+        left.push(work[0]);
+        right.push(work[n - 1 - level]);
     }
+    right.reverse();
+    (left, right)
+}
+
+/// Gets the roots in [0, 1] of the polynomial with the Bernstein coefficients
+/// c, sorted in increasing order.
+///
+/// A polynomial in Bernstein form has no more roots in the interval than its
+/// coefficients have sign changes, so the polynomial is split until each part
+/// either has no sign change or is small enough to locate the root.
+pub(crate) fn bernstein_roots(c: &[f64]) -> Vec<f64> {
+    let mut roots = Vec::new();
+    bernstein_roots_in(c, 0.0, 1.0, 0, &mut roots);
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| (*a - *b).abs() <= ROOT_EPSILON);
+    roots
+}
 
-    fn calc_curve(&mut self) {
-        let size = (1.0 / self.resolution + 1.0) as usize;
-        let mut curve = Vec::<Point>::with_capacity(size);
-        curve.push(Point {
-            x: self.ctrl_point[0].x,
-            y: self.ctrl_point[0].y,
-        });
+fn bernstein_roots_in(c: &[f64], t0: f64, t1: f64, depth: u32, roots: &mut Vec<f64>) {
+    let positive = c.iter().all(|v| *v > 0.0);
+    let negative = c.iter().all(|v| *v < 0.0);
+    let zero = c.iter().all(|v| *v == 0.0);
+    if positive || negative || zero {
+        return;
+    }
+    if c.len() == 2 {
+        // a line crossing zero
+        let t = if c[0] == c[1] {
+            0.0
+        } else {
+            c[0] / (c[0] - c[1])
+        };
+        roots.push(t0 + (t1 - t0) * t.clamp(0.0, 1.0));
+        return;
+    }
+    if depth >= ROOT_MAX_DEPTH || t1 - t0 <= ROOT_EPSILON {
+        roots.push((t0 + t1) * 0.5);
+        return;
+    }
+    let (left, right) = split_ctrl_points(c, 0.5);
+    let mid = (t0 + t1) * 0.5;
+    bernstein_roots_in(&left, t0, mid, depth + 1, roots);
+    bernstein_roots_in(&right, mid, t1, depth + 1, roots);
+}
 
-        for i in 1..size - 1 {
-            let t = self.resolution * (i - 1) as f32;
-            let x = self.ctrl_point[0].x * (1.0 - t).powf(3.0)
-                + self.ctrl_point[1].x * 3.0 * (1.0 - t).powf(2.0) * t
-                + self.ctrl_point[2].x * 3.0 * (1.0 - t) * t * t
-                + self.ctrl_point[3].x * t.powf(3.0);
-            let y = self.ctrl_point[0].y * (1.0 - t).powf(3.0)
-                + self.ctrl_point[1].y * 3.0 * (1.0 - t).powf(2.0) * t
-                + self.ctrl_point[2].y * 3.0 * (1.0 - t) * t * t
-                + self.ctrl_point[3].y * t.powf(3.0);
-            curve.push(Point { x, y });
+/// Solves the linear system matrix * x = rhs with Gaussian elimination and
+/// partial pivoting, or None if the matrix is singular.
+pub(crate) fn solve_linear(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<Point>) -> Option<Vec<Point>> {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))?;
+        if matrix[pivot][col].abs() <= f64::EPSILON {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = matrix[row][col] / matrix[col][col];
+            let (upper, lower) = matrix.split_at_mut(row);
+            for (target, source) in lower[0][col..].iter_mut().zip(&upper[col][col..]) {
+                *target -= factor * source;
+            }
+            rhs[row] = rhs[row] - rhs[col] * factor;
+        }
+    }
+    let mut x = vec![Point { x: 0.0, y: 0.0 }; n];
+    for row in (0..n).rev() {
+        let mut sum = rhs[row];
+        for k in row + 1..n {
+            sum = sum - x[k] * matrix[row][k];
         }
-        curve.push(Point {
-            x: self.ctrl_point[3].x,
-            y: self.ctrl_point[3].y,
-        });
-        self.modified = false;
-        self.curve = Some(curve);
+        x[row] = sum * (1.0 / matrix[row][row]);
+    }
+    Some(x)
+}
+
+/// Appends the end parameter of each flat part of the curve with the control
+/// points p, which spans t0 to t1 of the original curve.
+fn flatten_part(p: &[Point], t0: f64, t1: f64, tolerance: f64, depth: u32, params: &mut Vec<f64>) {
+    let (start, end) = (&p[0], &p[p.len() - 1]);
+    let flat = p[1..p.len() - 1]
+        .iter()
+        .all(|q| segment_distance(q, start, end) <= tolerance);
+    if flat || depth >= FLATTEN_MAX_DEPTH {
+        params.push(t1);
+    } else {
+        let (left, right) = split_ctrl_points(p, 0.5);
+        let mid = (t0 + t1) * 0.5;
+        flatten_part(&left, t0, mid, tolerance, depth + 1, params);
+        flatten_part(&right, mid, t1, tolerance, depth + 1, params);
+    }
+}
+
+/// Gets the distance from p to the line segment between start and end
+pub(crate) fn segment_distance(p: &Point, start: &Point, end: &Point) -> f64 {
+    let v = *end - *start;
+    let len2 = v.dot(&v);
+    if len2 <= f64::EPSILON {
+        return p.distance(start);
     }
+    let u = ((*p - *start).dot(&v) / len2).clamp(0.0, 1.0);
+    p.distance(&(*start + v * u))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    const TEST_EPSILON: f32 = 0.005;
+    use crate::{
+        fixtures::{arch, quarter_circle},
+        geometry::Line,
+    };
+    const TEST_EPSILON: f64 = 0.005;
 
     #[test]
     fn test_new() {
         let b = Bezier::new(0.25);
-        assert_eq!(0.25, b.resolution);
+        assert_eq!(0.25, b.tolerance);
     }
 
     #[test]
     fn test_new_with_points() {
-        let mut b = Bezier::new_with_ctrl_point(
+        let b = Bezier::new_with_ctrl_point(
             [
                 Point { x: 0.0, y: 0.0 },
                 Point { x: 1.0, y: 0.0 },
@@ -136,14 +546,364 @@ mod test {
             ],
             0.125,
         );
-        assert!((b.len() - 10.0).abs() < TEST_EPSILON);
+        assert!((b.length() - 10.0).abs() < TEST_EPSILON);
     }
 
     #[test]
-    fn test_set_resolution() {
+    fn test_set_tolerance() {
         let mut b = Bezier::new(0.25);
-        assert!(b.modified);
-        b.set_resolution(0.15);
-        assert_eq!(0.15, b.resolution);
+        assert!(b.curve.get().is_none());
+        b.set_tolerance(0.15);
+        assert_eq!(0.15, b.tolerance);
+    }
+
+    #[test]
+    fn test_point_at() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 10.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 10.0, y: 0.0 },
+            ],
+            0.125,
+        );
+        assert_eq!(b.point_at(0.0), Point { x: 0.0, y: 0.0 });
+        assert_eq!(b.point_at(1.0), Point { x: 10.0, y: 0.0 });
+        let mid = b.point_at(0.5);
+        assert!((mid.x - 5.0).abs() < TEST_EPSILON);
+        assert!((mid.y - 7.5).abs() < TEST_EPSILON);
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 10.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 10.0, y: 0.0 },
+            ],
+            0.125,
+        );
+        assert_eq!(b.derivative_at(0.0), Point { x: 0.0, y: 30.0 });
+        assert_eq!(b.derivative_at(1.0), Point { x: 0.0, y: -30.0 });
+        let d = b.derivative_at(0.5);
+        assert!((d.x - 15.0).abs() < TEST_EPSILON);
+        assert!(d.y.abs() < TEST_EPSILON);
+        assert_eq!(b.second_derivative_at(0.0), Point { x: 60.0, y: -60.0 });
+        assert_eq!(b.second_derivative_at(1.0), Point { x: -60.0, y: -60.0 });
+    }
+
+    #[test]
+    fn test_frames() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 10.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 10.0, y: 0.0 },
+            ],
+            0.25,
+        );
+        let frames = b.frames();
+        assert_eq!(frames[0].t, 0.0);
+        assert_eq!(frames.last().unwrap().t, 1.0);
+        assert_eq!(frames[0].tangent, Point { x: 0.0, y: 1.0 });
+        assert_eq!(frames[0].normal, Point { x: -1.0, y: 0.0 });
+        assert!((b.frame_at(0.5).tangent.x - 1.0).abs() < TEST_EPSILON);
+        for f in frames.iter() {
+            assert!((f.tangent.magnitude() - 1.0).abs() < TEST_EPSILON);
+            assert!(f.tangent.dot(&f.normal).abs() < TEST_EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_tangent_degenerate() {
+        // handles coincident with the endpoints
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 10.0, y: 10.0 },
+            ],
+            0.25,
+        );
+        let expected = Point { x: 1.0, y: 1.0 }.normalize().unwrap();
+        assert!(b.tangent_at(0.0).distance(&expected) < TEST_EPSILON);
+        assert!(b.tangent_at(1.0).distance(&expected) < TEST_EPSILON);
+
+        // all inner points on the start point
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 10.0 },
+            ],
+            0.25,
+        );
+        assert_eq!(b.tangent_at(0.0), Point { x: 0.0, y: 1.0 });
+
+        // a single point has no direction
+        let b = Bezier::new(0.25);
+        assert_eq!(b.tangent_at(0.5), Point { x: 1.0, y: 0.0 });
+        assert_eq!(b.normal_at(0.5), Point { x: 0.0, y: 1.0 });
+    }
+
+    #[test]
+    fn test_arc_length() {
+        // a quarter of a circle with radius 100 is approximately 157.08
+        let b = quarter_circle(0.25);
+        let table = b.arc_length_table(0.001);
+        assert!((table.length() - 157.08).abs() < 0.05);
+        assert!((b.arc_length(0.0, 1.0) - table.length()).abs() < 0.01);
+        assert_eq!(table.t_at_distance(0.0), 0.0);
+        assert_eq!(table.t_at_distance(table.length()), 1.0);
+        assert_eq!(table.t_at_distance(-10.0), 0.0);
+        assert_eq!(table.t_at_distance(1000.0), 1.0);
+    }
+
+    #[test]
+    fn test_point_at_distance() {
+        // uneven handles give a non-uniform parameterization of a line
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 1.0, y: 0.0 },
+                Point { x: 2.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+            ],
+            0.25,
+        );
+        let table = b.arc_length_table(0.001);
+        assert!((table.length() - 100.0).abs() < TEST_EPSILON);
+        for d in [5.0, 25.0, 50.0, 75.0, 99.0] {
+            let p = b.point_at_distance(&table, d);
+            assert!((p.x - d).abs() < 0.01, "{} != {}", p.x, d);
+            let t = table.t_at_distance(d);
+            assert!((table.distance_at(t) - d).abs() < 0.01);
+        }
+        assert_eq!(
+            b.tangent_at_distance(&table, 50.0),
+            Point { x: 1.0, y: 0.0 }
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 20.0, y: 10.0 },
+                Point { x: 30.0, y: 0.0 },
+            ],
+            0.1,
+        );
+        let length = b.length();
+        // moves along a single axis are applied
+        b.translate(5.0, 0.0);
+        assert_eq!(b.control_points()[0], Point { x: 5.0, y: 0.0 });
+        assert_eq!(b.curve()[0], Point { x: 5.0, y: 0.0 });
+        b.translate(0.0, -5.0);
+        assert_eq!(b.curve()[0], Point { x: 5.0, y: -5.0 });
+
+        // the cached curve and length follow the transform
+        b.rotate(std::f64::consts::FRAC_PI_2, Point { x: 5.0, y: -5.0 });
+        let end = *b.curve().last().unwrap();
+        assert!(end.distance(&Point { x: 5.0, y: 25.0 }) < 1e-4);
+        assert!((b.length() - length).abs() < 1e-3);
+        b.scale(2.0, Point { x: 5.0, y: -5.0 });
+        assert!((b.length() - length * 2.0).abs() < 1e-2);
+
+        // mirroring turns a left hand curve into a right hand curve
+        let k = b.curvature_at(0.5);
+        b.mirror(&Line::new(
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 0.0, y: 1.0 },
+        ));
+        assert!((b.curvature_at(0.5) + k).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_split() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 10.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 10.0, y: 0.0 },
+            ],
+            0.125,
+        );
+        let (left, right) = b.split(0.25);
+        assert_eq!(left.control_points()[0], b.control_points()[0]);
+        assert_eq!(right.control_points()[3], b.control_points()[3]);
+        assert_eq!(left.control_points()[3], b.point_at(0.25));
+        assert_eq!(right.control_points()[0], b.point_at(0.25));
+        for i in 0..=10 {
+            let u = i as f64 / 10.0;
+            assert!(left.point_at(u).distance(&b.point_at(0.25 * u)) < TEST_EPSILON);
+            assert!(right.point_at(u).distance(&b.point_at(0.25 + 0.75 * u)) < TEST_EPSILON);
+        }
+        assert!((left.length() + right.length() - b.arc_length(0.0, 1.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_subcurve() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 0.0, y: 10.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 10.0, y: 0.0 },
+            ],
+            0.125,
+        );
+        let part = b.subcurve(0.2, 0.6);
+        assert!(part.point_at(0.0).distance(&b.point_at(0.2)) < TEST_EPSILON);
+        assert!(part.point_at(1.0).distance(&b.point_at(0.6)) < TEST_EPSILON);
+        assert!(part.point_at(0.5).distance(&b.point_at(0.4)) < TEST_EPSILON);
+
+        let reversed = b.subcurve(0.6, 0.2);
+        assert!(reversed.point_at(0.0).distance(&b.point_at(0.6)) < TEST_EPSILON);
+        assert!(reversed.point_at(0.25).distance(&b.point_at(0.5)) < TEST_EPSILON);
+    }
+
+    #[test]
+    fn test_flatten() {
+        let line = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 10.0, y: 0.0 },
+                Point { x: 90.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+            ],
+            0.1,
+        );
+        assert_eq!(line.flatten(), vec![0.0, 1.0]);
+
+        let mut b = arch(1.0);
+        let coarse = b.curve().len();
+        b.set_tolerance(0.01);
+        let params = b.flatten();
+        assert!(b.curve().len() > coarse);
+        assert_eq!(b.curve().len(), params.len());
+        assert_eq!(b.curve()[0], Point { x: 0.0, y: 0.0 });
+        assert_eq!(*b.curve().last().unwrap(), Point { x: 100.0, y: 0.0 });
+        for w in params.windows(2) {
+            let start = b.point_at(w[0]);
+            let end = b.point_at(w[1]);
+            for i in 1..4 {
+                let p = b.point_at(w[0] + (w[1] - w[0]) * i as f64 / 4.0);
+                assert!(segment_distance(&p, &start, &end) <= 0.01 + TEST_EPSILON);
+            }
+        }
+        assert!((b.length() - b.arc_length(0.0, 1.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let b = arch(0.25);
+        let r = b.bounds();
+        assert_eq!((r.x, r.y, r.width), (0.0, 0.0, 100.0));
+        assert!((r.height - 75.0).abs() < TEST_EPSILON);
+
+        // the curve overshoots its end points on the x axis
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: -50.0, y: 10.0 },
+                Point { x: 150.0, y: 20.0 },
+                Point { x: 100.0, y: 30.0 },
+            ],
+            0.25,
+        );
+        let r = b.bounds();
+        for i in 0..=100 {
+            let p = b.point_at(i as f64 / 100.0);
+            assert!(r.inflate(TEST_EPSILON).contains(p));
+        }
+        assert!(r.x < -5.0 && r.x + r.width > 105.0);
+        assert_eq!((r.y, r.height), (0.0, 30.0));
+    }
+
+    #[test]
+    fn test_linear() {
+        let b = Bezier::new_with_ctrl_point(
+            [Point { x: 0.0, y: 0.0 }, Point { x: 30.0, y: 40.0 }],
+            0.1,
+        );
+        assert_eq!(b.degree(), 1);
+        assert_eq!(b.point_at(0.5), Point { x: 15.0, y: 20.0 });
+        assert_eq!(b.derivative_at(0.2), Point { x: 30.0, y: 40.0 });
+        assert_eq!(b.second_derivative_at(0.2), Point { x: 0.0, y: 0.0 });
+        assert_eq!(b.curvature_at(0.5), 0.0);
+        assert_eq!(b.flatten(), vec![0.0, 1.0]);
+        assert!((b.length() - 50.0).abs() < 1e-9);
+        let r = b.bounds();
+        assert_eq!((r.x, r.y, r.width, r.height), (0.0, 0.0, 30.0, 40.0));
+        assert!(b.reduce_degree().is_none());
+    }
+
+    #[test]
+    fn test_quadratic() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 50.0, y: 100.0 },
+                Point { x: 100.0, y: 0.0 },
+            ],
+            0.1,
+        );
+        assert_eq!(b.degree(), 2);
+        assert_eq!(b.point_at(0.5), Point { x: 50.0, y: 50.0 });
+        assert_eq!(b.derivative_at(0.0), Point { x: 100.0, y: 200.0 });
+        assert_eq!(b.second_derivative_at(0.7), Point { x: 0.0, y: -400.0 });
+        assert_eq!(b.nth_derivative_at(3, 0.5), Point { x: 0.0, y: 0.0 });
+        let r = b.bounds();
+        assert!((r.height - 50.0).abs() < TEST_EPSILON);
+        let (left, right) = b.split(0.5);
+        assert_eq!(left.degree(), 2);
+        assert_eq!(right.control_points()[0], Point { x: 50.0, y: 50.0 });
+    }
+
+    #[test]
+    fn test_elevate_degree() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 50.0, y: 100.0 },
+                Point { x: 100.0, y: 0.0 },
+            ],
+            0.1,
+        );
+        let cubic = b.elevate_degree();
+        assert_eq!(cubic.degree(), 3);
+        let quartic = cubic.elevate_degree();
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            assert!(cubic.point_at(t).distance(&b.point_at(t)) < TEST_EPSILON);
+            assert!(quartic.point_at(t).distance(&b.point_at(t)) < TEST_EPSILON);
+        }
+        let reduced = quartic.reduce_degree().unwrap().reduce_degree().unwrap();
+        assert_eq!(reduced.degree(), 2);
+        for (p, q) in reduced.control_points().iter().zip(b.control_points()) {
+            assert!(p.distance(q) < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_reduce_degree() {
+        let b = arch(0.1);
+        let quadratic = b.reduce_degree().unwrap();
+        assert_eq!(quadratic.degree(), 2);
+        assert_eq!(quadratic.control_points()[0], b.control_points()[0]);
+        assert_eq!(quadratic.control_points()[2], b.control_points()[3]);
+        // the best quadratic is symmetric and close to the cubic
+        assert!((quadratic.control_points()[1].x - 50.0).abs() < 0.01);
+        assert!(quadratic.point_at(0.5).distance(&b.point_at(0.5)) < 10.0);
     }
 }
@@ -0,0 +1,274 @@
+use crate::{
+    bezier::{segment_distance, Bezier},
+    curve::Curve,
+    geometry::{Arc, Line, Point},
+    track::TrackCurve,
+};
+
+/// Limits the subdivision depth when approximating a curve with biarcs
+const BIARC_MAX_DEPTH: u32 = 12;
+/// Parameters of the points used to measure the approximation error
+const ERROR_SAMPLES: [f64; 7] = [0.0625, 0.125, 0.25, 0.5, 0.75, 0.875, 0.9375];
+/// Tangents with a larger dot product than this are parallel
+const PARALLEL_EPSILON: f64 = 1.0 - 1e-6;
+/// Arcs whose end is closer to the start tangent line than this fraction of
+/// the chord are straight
+const STRAIGHT_EPSILON: f64 = 1e-6;
+
+/// A part of a curve approximated with arcs. Straight parts of the curve
+/// have no finite radius and are lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArcSegment {
+    Line(Line),
+    Arc(Arc),
+}
+
+impl ArcSegment {
+    pub fn start(&self) -> Point {
+        match self {
+            ArcSegment::Line(line) => line.start,
+            ArcSegment::Arc(arc) => arc.start(),
+        }
+    }
+
+    pub fn end(&self) -> Point {
+        match self {
+            ArcSegment::Line(line) => line.end,
+            ArcSegment::Arc(arc) => arc.end(),
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        match self {
+            ArcSegment::Line(line) => line.length(),
+            ArcSegment::Arc(arc) => arc.length(),
+        }
+    }
+
+    /// Gets the distance from p to the closest point on the segment
+    pub fn distance_to(&self, p: &Point) -> f64 {
+        match self {
+            ArcSegment::Line(line) => segment_distance(p, &line.start, &line.end),
+            ArcSegment::Arc(arc) => arc.distance_to(p),
+        }
+    }
+}
+
+impl Bezier {
+    /// Approximates the curve with a chain of circular arcs that stays
+    /// within tolerance layout units of the curve. Consecutive arcs meet
+    /// with the same tangent.
+    ///
+    /// The curve is split at its inflections, since a biarc turns one way
+    /// and then, at most, the other. Each part is fitted with a biarc, a
+    /// pair of arcs that matches the end points and end tangents of the part
+    /// and meet each other tangentially, and parts that are not within
+    /// tolerance are split in half.
+    pub fn to_arcs(&self, tolerance: f64) -> Vec<ArcSegment> {
+        let mut bounds = vec![0.0];
+        if let Some(analysis) = self.analyze() {
+            bounds.extend(analysis.inflections);
+        }
+        bounds.push(1.0);
+        let mut arcs = Vec::new();
+        for pair in bounds.windows(2) {
+            fit_biarc(self, pair[0], pair[1], tolerance, 0, &mut arcs);
+        }
+        arcs
+    }
+}
+
+/// Appends the biarcs that approximate the curve between t0 and t1 to arcs
+fn fit_biarc(
+    curve: &Bezier,
+    t0: f64,
+    t1: f64,
+    tolerance: f64,
+    depth: u32,
+    arcs: &mut Vec<ArcSegment>,
+) {
+    let biarc = biarc(
+        curve.point_at(t0),
+        curve.tangent_at(t0),
+        curve.point_at(t1),
+        curve.tangent_at(t1),
+    );
+    let error = ERROR_SAMPLES
+        .iter()
+        .map(|s| {
+            let p = curve.point_at(t0 + (t1 - t0) * s);
+            biarc
+                .iter()
+                .map(|a| a.distance_to(&p))
+                .fold(f64::INFINITY, f64::min)
+        })
+        .fold(0.0, f64::max);
+    if error <= tolerance || depth >= BIARC_MAX_DEPTH {
+        arcs.extend(biarc);
+    } else {
+        let mid = (t0 + t1) * 0.5;
+        fit_biarc(curve, t0, mid, tolerance, depth + 1, arcs);
+        fit_biarc(curve, mid, t1, tolerance, depth + 1, arcs);
+    }
+}
+
+/// Gets the biarc from p0 with the unit tangent t0 to p1 with the unit
+/// tangent t1.
+///
+/// The arcs meet at the midpoint of the control points p0 + d * t0 and
+/// p1 - d * t1, where the equal distances d are chosen so that the control
+/// points are 2 * d apart:
+///      |v - d * (t0 + t1)|^2 = 4 * d^2
+/// with v = p1 - p0. The biarc is tangent to the line through the control
+/// points at the joint.
+///
+/// With parallel tangents the joint is the midpoint of the chord, where the
+/// two arcs form an S-curve, unless the whole biarc is a straight line.
+fn biarc(p0: Point, t0: Point, p1: Point, t1: Point) -> Vec<ArcSegment> {
+    let joint = if t0.dot(&t1) > PARALLEL_EPSILON {
+        let single = arc_to(p0, t0, p1);
+        if matches!(single, ArcSegment::Line(_)) {
+            return vec![single];
+        }
+        (p0 + p1) * 0.5
+    } else {
+        let v = p1 - p0;
+        let vt = v.dot(&(t0 + t1));
+        let denominator = 2.0 * (1.0 - t0.dot(&t1));
+        let d = (-vt + (vt * vt + denominator * v.dot(&v)).sqrt()) / denominator;
+        (p0 + t0 * d + p1 - t1 * d) * 0.5
+    };
+    vec![arc_to(p0, t0, joint), arc_to(p1, -t1, joint).reverse()]
+}
+
+/// Gets the arc that starts at p with the unit tangent t and ends at q, or
+/// the line to q if the arc is straight.
+fn arc_to(p: Point, t: Point, q: Point) -> ArcSegment {
+    let n = t.perpendicular();
+    let chord = q - p;
+    let offset = n.dot(&chord);
+    if offset.abs() <= STRAIGHT_EPSILON * chord.magnitude() {
+        return ArcSegment::Line(Line::new(p, q));
+    }
+    // signed distance along the normal to the center, which is the same
+    // distance from p and q
+    let s = chord.dot(&chord) / (2.0 * offset);
+    let center = p + n * s;
+    let (a, b) = (p - center, q - center);
+    let mut sweep = a.cross(&b).atan2(a.dot(&b));
+    if s > 0.0 && sweep < 0.0 {
+        sweep += 2.0 * std::f64::consts::PI;
+    } else if s < 0.0 && sweep > 0.0 {
+        sweep -= 2.0 * std::f64::consts::PI;
+    }
+    ArcSegment::Arc(Arc::new(center, s.abs(), a.y.atan2(a.x), sweep))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::quarter_circle;
+
+    fn assert_chain(arcs: &[ArcSegment]) {
+        for pair in arcs.windows(2) {
+            assert!(pair[0].end().distance(&pair[1].start()) < 1e-2);
+            if let (ArcSegment::Arc(a), ArcSegment::Arc(b)) = (pair[0], pair[1]) {
+                assert!((a.tangent_at(1.0) - b.tangent_at(0.0)).magnitude() < 1e-3);
+            }
+        }
+    }
+
+    /// Checks that the chain of arcs leaves along t0 and arrives along t1
+    fn assert_tangents(arcs: &[ArcSegment], t0: Point, t1: Point) {
+        let (first, last) = (arcs[0], arcs[arcs.len() - 1]);
+        assert!((first.tangent_along(0.0) - t0).magnitude() < 1e-3);
+        assert!((last.tangent_along(last.length()) - t1).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn test_circle() {
+        let b = quarter_circle(0.1);
+        let arcs = b.to_arcs(0.05);
+        assert_chain(&arcs);
+        assert_tangents(&arcs, b.tangent_at(0.0), b.tangent_at(1.0));
+        assert_eq!(arcs[0].start(), b.point_at(0.0));
+        assert!(arcs.last().unwrap().end().distance(&b.point_at(1.0)) < 1e-3);
+        for segment in &arcs {
+            let ArcSegment::Arc(arc) = segment else {
+                panic!("expected an arc");
+            };
+            assert!((arc.radius - 100.0).abs() < 1.0);
+            assert!(arc.sweep > 0.0);
+        }
+        let length: f64 = arcs.iter().map(|a| a.length()).sum();
+        assert!((length - b.length()).abs() < 0.1);
+        for i in 0..=20 {
+            let p = b.point_at(i as f64 / 20.0);
+            let d = arcs
+                .iter()
+                .map(|a| a.distance_to(&p))
+                .fold(f64::INFINITY, f64::min);
+            assert!(d <= 0.05);
+        }
+    }
+
+    #[test]
+    fn test_s_curve() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+                Point { x: 0.0, y: 100.0 },
+                Point { x: 100.0, y: 100.0 },
+            ],
+            0.1,
+        );
+        let arcs = b.to_arcs(0.1);
+        assert_chain(&arcs);
+        assert_tangents(&arcs, b.tangent_at(0.0), b.tangent_at(1.0));
+        let sweeps: Vec<f64> = arcs
+            .iter()
+            .filter_map(|a| match a {
+                ArcSegment::Arc(arc) => Some(arc.sweep),
+                ArcSegment::Line(_) => None,
+            })
+            .collect();
+        assert!(sweeps.iter().any(|s| *s > 0.0));
+        assert!(sweeps.iter().any(|s| *s < 0.0));
+    }
+
+    #[test]
+    fn test_straight() {
+        let b = Bezier::new_with_ctrl_point(
+            [Point { x: 0.0, y: 0.0 }, Point { x: 30.0, y: 40.0 }],
+            0.1,
+        );
+        let arcs = b.to_arcs(0.1);
+        assert_eq!(arcs.len(), 1);
+        assert!(matches!(arcs[0], ArcSegment::Line(_)));
+        assert!((arcs[0].length() - 50.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_parallel_tangents() {
+        let t = Point { x: 1.0, y: 0.0 };
+        let start = Point { x: 0.0, y: 0.0 };
+        // a lane change is an S-curve that meets in the middle
+        let end = Point { x: 100.0, y: 50.0 };
+        let arcs = biarc(start, t, end, t);
+        assert_eq!(arcs.len(), 2);
+        assert_chain(&arcs);
+        assert_tangents(&arcs, t, t);
+        assert!(arcs[0].end().distance(&Point { x: 50.0, y: 25.0 }) < 1e-6);
+        // the end can be behind the start
+        let end = Point { x: -100.0, y: 50.0 };
+        let arcs = biarc(start, t, end, t);
+        assert_chain(&arcs);
+        assert_tangents(&arcs, t, t);
+        assert!(arcs[1].end().distance(&end) < 1e-6);
+        // and a biarc along the tangent is straight
+        let arcs = biarc(start, t, Point { x: 100.0, y: 1e-7 }, t);
+        assert_eq!(arcs.len(), 1);
+        assert!(matches!(arcs[0], ArcSegment::Line(_)));
+    }
+}
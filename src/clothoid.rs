@@ -0,0 +1,520 @@
+use std::f64::consts::PI;
+
+use crate::{
+    bezier::Bezier,
+    curve::Curve,
+    geometry::{Line, Point},
+};
+
+/// Spirals whose curvature changes by less than this over their length are
+/// evaluated as circular arcs, where the Fresnel form is ill conditioned.
+const CONSTANT_CURVATURE_EPSILON: f64 = 1e-9;
+/// Curvatures smaller than this are straight
+const STRAIGHT_CURVATURE: f64 = 1e-12;
+/// The Fresnel integrals use their power series below this argument and
+/// their asymptotic expansion above it, where both are accurate to about
+/// 1e-8.
+const FRESNEL_SERIES_LIMIT: f64 = 3.5;
+/// Limits the number of terms of the Fresnel series expansions
+const FRESNEL_MAX_TERMS: usize = 128;
+/// Limits the subdivision depth when converting the curve to beziers
+const BEZIER_MAX_DEPTH: u32 = 12;
+/// Parameters of the interior points used to measure the error of a bezier
+const BEZIER_ERROR_SAMPLES: [f64; 5] = [0.1, 0.25, 0.5, 0.75, 0.9];
+
+/// A clothoid, or Euler spiral, whose curvature changes linearly with the
+/// distance along it:
+///
+///      k(s) = k0 + (k1 - k0) * s / L
+/// Integrating the curvature gives the heading
+///      θ(s) = θ0 + k0 * s + (k1 - k0) * s^2 / (2 * L)
+/// and the position is the integral of (cos θ(s), sin θ(s)), which is
+/// evaluated with the Fresnel integrals. A clothoid with equal end
+/// curvatures is a circular arc or a straight line.
+///
+/// As a `Curve` the parameter t is the fraction of the length, so the curve
+/// is parameterized by arc length.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clothoid {
+    start: Point,
+    heading: f64,
+    start_curvature: f64,
+    end_curvature: f64,
+    length: f64,
+    tolerance: f64,
+}
+
+impl Clothoid {
+    /// Creates the clothoid that starts at start in the direction of the
+    /// heading in radians and whose curvature changes from start_curvature
+    /// to end_curvature over the length. Positive curvatures turn
+    /// counter-clockwise.
+    pub fn new(
+        start: Point,
+        heading: f64,
+        start_curvature: f64,
+        end_curvature: f64,
+        length: f64,
+        tolerance: f64,
+    ) -> Self {
+        assert!(length >= 0.0, "a clothoid cannot have a negative length");
+        Clothoid {
+            start,
+            heading,
+            start_curvature,
+            end_curvature,
+            length,
+            tolerance,
+        }
+    }
+
+    /// Creates the easement curves and circular arc that join the entry
+    /// line to the exit line. Both lines point in the direction of travel.
+    ///
+    /// The entry spiral eases the curvature from straight to 1 / radius over
+    /// the spiral length, the arc keeps that radius, and the exit spiral
+    /// eases back to straight. Each spiral turns through
+    ///      θs = spiral_length / (2 * radius)
+    /// so the arc turns through the rest of the deflection Δ between the
+    /// lines. The spirals shift the arc inwards, and the curve starts on the
+    /// entry line at the distance
+    ///      (R + p) * tan(Δ / 2) + k
+    /// before the intersection of the lines, where (k, R + p) is the center
+    /// of the arc relative to the start of the entry spiral. With a spiral
+    /// length of 0 the transition is just the arc.
+    ///
+    /// Gets None if the lines are parallel or the spirals would turn through
+    /// more than the deflection, if the radius is not positive or the spiral
+    /// length is negative, or if any of the inputs are not finite.
+    pub fn transition(
+        entry: &Line,
+        exit: &Line,
+        radius: f64,
+        spiral_length: f64,
+        tolerance: f64,
+    ) -> Option<Vec<Clothoid>> {
+        let finite = [entry.start, entry.end, exit.start, exit.end]
+            .iter()
+            .all(|p| p.x.is_finite() && p.y.is_finite());
+        if !finite
+            || !(radius > 0.0 && radius.is_finite())
+            || !(spiral_length >= 0.0 && spiral_length.is_finite())
+            || !tolerance.is_finite()
+        {
+            return None;
+        }
+        let d_in = (entry.end - entry.start).normalize()?;
+        let d_out = (exit.end - exit.start).normalize()?;
+        let cross = d_in.cross(&d_out);
+        let deflection = cross.atan2(d_in.dot(&d_out));
+        let turn = deflection.signum();
+        let deflection = deflection.abs();
+        let spiral_angle = spiral_length / (2.0 * radius);
+        if cross.abs() <= f64::EPSILON || 2.0 * spiral_angle > deflection {
+            return None;
+        }
+        // intersection of the lines
+        let w = exit.start - entry.start;
+        let vertex = entry.start + d_in * (w.cross(&d_out) / cross);
+
+        // spiral end relative to its start with the entry along the x axis
+        let local = Clothoid::new(
+            Point { x: 0.0, y: 0.0 },
+            0.0,
+            0.0,
+            1.0 / radius,
+            spiral_length,
+            tolerance,
+        )
+        .point_at(1.0);
+        let k = local.x - radius * spiral_angle.sin();
+        let p = local.y - radius * (1.0 - spiral_angle.cos());
+        let tangent_distance = (radius + p) * (deflection * 0.5).tan() + k;
+
+        let start = vertex - d_in * tangent_distance;
+        let heading = d_in.y.atan2(d_in.x);
+        let curvature = turn / radius;
+        let arc_length = radius * (deflection - 2.0 * spiral_angle);
+        let mut curves = Vec::new();
+        if spiral_length > 0.0 {
+            curves.push(Clothoid::new(
+                start,
+                heading,
+                0.0,
+                curvature,
+                spiral_length,
+                tolerance,
+            ));
+        }
+        if arc_length > 0.0 {
+            let (start, heading) = curves.last().map_or((start, heading), |c| {
+                (c.point_at(1.0), c.heading_at(c.length))
+            });
+            curves.push(Clothoid::new(
+                start, heading, curvature, curvature, arc_length, tolerance,
+            ));
+        }
+        if spiral_length > 0.0 {
+            let last = curves.last().unwrap();
+            curves.push(Clothoid::new(
+                last.point_at(1.0),
+                last.heading_at(last.length),
+                curvature,
+                0.0,
+                spiral_length,
+                tolerance,
+            ));
+        }
+        Some(curves)
+    }
+
+    pub fn start(&self) -> Point {
+        self.start
+    }
+
+    pub fn heading(&self) -> f64 {
+        self.heading
+    }
+
+    pub fn start_curvature(&self) -> f64 {
+        self.start_curvature
+    }
+
+    pub fn end_curvature(&self) -> f64 {
+        self.end_curvature
+    }
+
+    /// Gets the rate of change of the curvature with distance
+    pub fn curvature_rate(&self) -> f64 {
+        if self.length <= 0.0 {
+            return 0.0;
+        }
+        (self.end_curvature - self.start_curvature) / self.length
+    }
+
+    /// Gets the heading in radians at the distance s along the curve
+    pub fn heading_at(&self, s: f64) -> f64 {
+        self.heading + self.start_curvature * s + self.curvature_rate() * s * s * 0.5
+    }
+
+    /// Gets the cubic beziers that stay within tolerance of the curve, for
+    /// rendering and editing.
+    pub fn to_beziers(&self, tolerance: f64) -> Vec<Bezier> {
+        let mut curves = Vec::new();
+        self.fit_beziers(0.0, 1.0, tolerance, 0, &mut curves);
+        curves
+    }
+
+    /// Approximates the curve between t0 and t1 with the cubic hermite
+    /// curve that matches the position and direction of the curve at both
+    /// ends. As the curve is parameterized by arc length each handle is a
+    /// third of the length between t0 and t1. The cubic is split until it
+    /// is within tolerance of the points at the same parameters.
+    fn fit_beziers(&self, t0: f64, t1: f64, tolerance: f64, depth: u32, curves: &mut Vec<Bezier>) {
+        let handle = (t1 - t0) * self.length / 3.0;
+        let start = self.point_at(t0);
+        let end = self.point_at(t1);
+        let candidate = Bezier::new_with_ctrl_point(
+            [
+                start,
+                start + self.tangent_at(t0) * handle,
+                end - self.tangent_at(t1) * handle,
+                end,
+            ],
+            self.tolerance,
+        );
+        let within = BEZIER_ERROR_SAMPLES.iter().all(|u| {
+            let p = self.point_at(t0 + (t1 - t0) * u);
+            candidate.point_at(*u).distance(&p) <= tolerance
+        });
+        if within || depth >= BEZIER_MAX_DEPTH {
+            curves.push(candidate);
+        } else {
+            let mid = (t0 + t1) * 0.5;
+            self.fit_beziers(t0, mid, tolerance, depth + 1, curves);
+            self.fit_beziers(mid, t1, tolerance, depth + 1, curves);
+        }
+    }
+
+    /// Gets the point at the distance s along the curve
+    fn point_at_distance_exact(&self, s: f64) -> Point {
+        let theta = self.heading;
+        let k0 = self.start_curvature;
+        let rate = self.curvature_rate();
+        let (x, y) = if (rate * self.length).abs() <= CONSTANT_CURVATURE_EPSILON {
+            if k0.abs() <= STRAIGHT_CURVATURE {
+                (s * theta.cos(), s * theta.sin())
+            } else {
+                let end = theta + k0 * s;
+                (
+                    (end.sin() - theta.sin()) / k0,
+                    (theta.cos() - end.cos()) / k0,
+                )
+            }
+        } else {
+            // complete the square of the heading about the point of zero
+            // curvature, u = s + k0 / rate, so θ = alpha + rate * u^2 / 2
+            let alpha = theta - k0 * k0 / (2.0 * rate);
+            let scale = (PI / rate.abs()).sqrt();
+            let u0 = k0 / rate;
+            let (c0, s0) = fresnel(u0 / scale);
+            let (c1, s1) = fresnel((u0 + s) / scale);
+            let (dc, ds) = (c1 - c0, (s1 - s0) * rate.signum());
+            (
+                scale * (alpha.cos() * dc - alpha.sin() * ds),
+                scale * (alpha.sin() * dc + alpha.cos() * ds),
+            )
+        };
+        self.start + Point { x, y }
+    }
+}
+
+impl Curve for Clothoid {
+    fn point_at(&self, t: f64) -> Point {
+        let s = t.clamp(0.0, 1.0) * self.length;
+        self.point_at_distance_exact(s)
+    }
+
+    fn derivative_at(&self, t: f64) -> Point {
+        let theta = self.heading_at(t.clamp(0.0, 1.0) * self.length);
+        Point {
+            x: theta.cos(),
+            y: theta.sin(),
+        } * self.length
+    }
+
+    fn second_derivative_at(&self, t: f64) -> Point {
+        let s = t.clamp(0.0, 1.0) * self.length;
+        let theta = self.heading_at(s);
+        let k = self.start_curvature + self.curvature_rate() * s;
+        Point {
+            x: -theta.sin(),
+            y: theta.cos(),
+        } * (self.length * self.length * k)
+    }
+
+    /// Gets evenly spaced parameters whose chords are short enough for the
+    /// sagitta h^2 * k / 8 at the largest curvature to stay within the
+    /// tolerance.
+    fn flatten(&self) -> Vec<f64> {
+        let k = self.start_curvature.abs().max(self.end_curvature.abs());
+        let count = if k > 0.0 {
+            let chord = (8.0 * self.tolerance / k).sqrt();
+            (self.length / chord).ceil().max(1.0) as usize
+        } else {
+            1
+        };
+        (0..=count).map(|i| i as f64 / count as f64).collect()
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    fn arc_length(&self, t0: f64, t1: f64) -> f64 {
+        (t1 - t0) * self.length
+    }
+}
+
+/// Gets the Fresnel integrals
+///
+///      C(x) = ∫ cos(π * t^2 / 2) dt,  S(x) = ∫ sin(π * t^2 / 2) dt
+/// from 0 to x.
+///
+/// Small arguments use the power series
+///      C(x) + i * S(x) = sum (i * π / 2)^k * x^(2k + 1) / (k! * (2k + 1))
+/// and large arguments the asymptotic expansion with the auxiliary
+/// functions f and g of Abramowitz and Stegun 7.3.27 and 7.3.28.
+fn fresnel(x: f64) -> (f64, f64) {
+    let sign = x.signum();
+    let x = x.abs();
+    if x <= FRESNEL_SERIES_LIMIT {
+        let y = PI * x * x * 0.5;
+        let (mut c, mut s) = (0.0, 0.0);
+        let mut power = x;
+        for k in 0..FRESNEL_MAX_TERMS {
+            if k > 0 {
+                power *= y / k as f64;
+            }
+            let term = power / (2 * k + 1) as f64;
+            // the powers of i cycle through 1, i, -1, -i
+            match k % 4 {
+                0 => c += term,
+                1 => s += term,
+                2 => c -= term,
+                _ => s -= term,
+            }
+            if term <= f64::EPSILON * (c.abs() + s.abs()) * 1e-2 {
+                break;
+            }
+        }
+        return (sign * c, sign * s);
+    }
+    let z = PI * x * x;
+    let (mut f, mut g) = (0.0, 0.0);
+    let (mut f_term, mut g_term) = (1.0, 1.0);
+    for m in 0..FRESNEL_MAX_TERMS {
+        f += f_term;
+        g += g_term;
+        let n = (m + 1) as f64;
+        let next_f = -f_term * (4.0 * n - 1.0) * (4.0 * n - 3.0) / (z * z);
+        let next_g = -g_term * (4.0 * n + 1.0) * (4.0 * n - 1.0) / (z * z);
+        // an asymptotic series is most accurate just before its terms grow
+        if next_f.abs() >= f_term.abs() || next_f.abs() <= f64::EPSILON {
+            break;
+        }
+        f_term = next_f;
+        g_term = next_g;
+    }
+    let f = f / (PI * x);
+    let g = g / (PI * x * z);
+    let (sin, cos) = (z * 0.5).sin_cos();
+    (
+        sign * (0.5 + f * sin - g * cos),
+        sign * (0.5 - f * cos - g * sin),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::distance_to_curves;
+
+    #[test]
+    fn test_fresnel() {
+        let (c, s) = fresnel(1.0);
+        assert!((c - 0.779_893_400_4).abs() < 1e-8);
+        assert!((s - 0.438_259_147_4).abs() < 1e-8);
+        let (c, s) = fresnel(-1.0);
+        assert!((c + 0.779_893_400_4).abs() < 1e-8);
+        assert!((s + 0.438_259_147_4).abs() < 1e-8);
+        // either side of the switch to the asymptotic expansion
+        let (c, s) = fresnel(5.0);
+        assert!((c - 0.563_631_188_6).abs() < 1e-8);
+        assert!((s - 0.499_191_381_7).abs() < 1e-8);
+        let below = fresnel(FRESNEL_SERIES_LIMIT);
+        let above = fresnel(FRESNEL_SERIES_LIMIT + 1e-9);
+        assert!((below.0 - above.0).abs() < 1e-7 && (below.1 - above.1).abs() < 1e-7);
+        assert_eq!(fresnel(0.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_constant_curvature() {
+        let origin = Point { x: 0.0, y: 0.0 };
+        let line = Clothoid::new(origin, 0.5, 0.0, 0.0, 100.0, 0.1);
+        let end = line.point_at(1.0);
+        assert!((end.x - 100.0 * 0.5_f64.cos()).abs() < 1e-4);
+        assert!((end.y - 100.0 * 0.5_f64.sin()).abs() < 1e-4);
+        assert_eq!(line.flatten(), vec![0.0, 1.0]);
+
+        let radius = 457.2;
+        let arc = Clothoid::new(origin, 0.0, 1.0 / radius, 1.0 / radius, 300.0, 0.1);
+        let center = Point { x: 0.0, y: radius };
+        for i in 0..=10 {
+            let p = arc.point_at(i as f64 / 10.0);
+            assert!((p.distance(&center) - radius).abs() < 1e-3);
+        }
+        assert!((arc.radius_at(0.5) - radius).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_spiral() {
+        let spiral = Clothoid::new(Point { x: 10.0, y: 5.0 }, 0.3, 0.0, 0.01, 200.0, 0.1);
+        assert_eq!(spiral.length(), 200.0);
+        assert_eq!(spiral.point_at(0.0), Point { x: 10.0, y: 5.0 });
+        // curvature grows linearly with distance
+        for i in 0..=4 {
+            let t = i as f64 / 4.0;
+            assert!((spiral.curvature_at(t) - 0.01 * t).abs() < 1e-6);
+        }
+        assert!((spiral.heading_at(200.0) - (0.3 + 1.0)).abs() < 1e-6);
+        // the derivative matches the change in position
+        let h = 1e-3;
+        let d = (spiral.point_at(0.5 + h) - spiral.point_at(0.5 - h)) * (0.5 / h);
+        assert!((d - spiral.derivative_at(0.5)).magnitude() < 0.05);
+        // the chord is shorter than the length
+        let chord = spiral.point_at(0.0).distance(&spiral.point_at(1.0));
+        assert!(chord < 200.0 && chord > 190.0);
+
+        let beziers = spiral.to_beziers(0.05);
+        for i in 0..=20 {
+            let p = spiral.point_at(i as f64 / 20.0);
+            assert!(distance_to_curves(&beziers, &p) <= 0.05);
+        }
+        // the beziers join end to end with the direction of the spiral
+        assert_eq!(beziers[0].point_at(0.0), spiral.point_at(0.0));
+        for pair in beziers.windows(2) {
+            assert_eq!(pair[0].point_at(1.0), pair[1].point_at(0.0));
+            assert!((pair[0].tangent_at(1.0) - pair[1].tangent_at(0.0)).magnitude() < 1e-9);
+        }
+        let last = beziers.last().unwrap();
+        assert!((last.tangent_at(1.0) - spiral.tangent_at(1.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn test_transition() {
+        let entry = Line::new(Point { x: 0.0, y: 0.0 }, Point { x: 1000.0, y: 0.0 });
+        let angle = std::f64::consts::FRAC_PI_3;
+        let exit_direction = Point {
+            x: angle.cos(),
+            y: angle.sin(),
+        };
+        let vertex = Point { x: 1000.0, y: 0.0 };
+        let exit = Line::new(vertex, vertex + exit_direction * 1000.0);
+        let curves = Clothoid::transition(&entry, &exit, 500.0, 200.0, 0.1).unwrap();
+        assert_eq!(curves.len(), 3);
+
+        let first = &curves[0];
+        assert!(first.point_at(0.0).y.abs() < 1e-3);
+        assert!(first.point_at(0.0).x < 1000.0);
+        assert_eq!(first.curvature_at(0.0), 0.0);
+        assert!((curves[1].curvature_at(0.5) - 1.0 / 500.0).abs() < 1e-6);
+        for pair in curves.windows(2) {
+            assert!(pair[0].point_at(1.0).distance(&pair[1].point_at(0.0)) < 1e-3);
+            assert!((pair[0].curvature_at(1.0) - pair[1].curvature_at(0.0)).abs() < 1e-6);
+        }
+
+        let last = curves.last().unwrap();
+        let end = last.point_at(1.0);
+        assert!((end - vertex).cross(&exit_direction).abs() < 0.05);
+        assert!((last.tangent_at(1.0) - exit_direction).magnitude() < 1e-4);
+        assert!(last.curvature_at(1.0).abs() < 1e-6);
+        // symmetric about the bisector of the lines
+        assert!((end.distance(&vertex) - first.point_at(0.0).distance(&vertex)).abs() < 0.05);
+
+        // right hand curves turn the other way
+        let exit = Line::new(vertex, vertex + Point { x: 1.0, y: -1.0 });
+        let curves = Clothoid::transition(&entry, &exit, 500.0, 200.0, 0.1).unwrap();
+        assert!(curves[1].curvature_at(0.5) < 0.0);
+
+        // the spirals alone would turn through more than the lines
+        let exit = Line::new(vertex, vertex + Point { x: 1.0, y: 0.1 });
+        assert!(Clothoid::transition(&entry, &exit, 500.0, 200.0, 0.1).is_none());
+
+        // the radius and spiral length must make sense
+        let exit = Line::new(vertex, vertex + exit_direction * 1000.0);
+        assert!(Clothoid::transition(&entry, &exit, 0.0, 200.0, 0.1).is_none());
+        assert!(Clothoid::transition(&entry, &exit, -500.0, 200.0, 0.1).is_none());
+        assert!(Clothoid::transition(&entry, &exit, 500.0, -200.0, 0.1).is_none());
+        assert!(Clothoid::transition(&entry, &exit, f64::NAN, 200.0, 0.1).is_none());
+        assert!(Clothoid::transition(&entry, &exit, 500.0, f64::INFINITY, 0.1).is_none());
+        assert!(Clothoid::transition(&entry, &exit, 500.0, 200.0, f64::NAN).is_none());
+        let far = Line::new(
+            vertex,
+            Point {
+                x: f64::INFINITY,
+                y: 0.0,
+            },
+        );
+        assert!(Clothoid::transition(&entry, &far, 500.0, 200.0, 0.1).is_none());
+        // without spirals the curve is a plain arc
+        let curves = Clothoid::transition(&entry, &exit, 500.0, 0.0, 0.1).unwrap();
+        assert_eq!(curves.len(), 1);
+        let arc = &curves[0];
+        assert_eq!(arc.start_curvature(), 1.0 / 500.0);
+        assert_eq!(arc.end_curvature(), 1.0 / 500.0);
+        assert!((arc.length() - 500.0 * angle).abs() < 1e-6);
+        assert!((arc.point_at(0.0).distance(&vertex) - 500.0 * (angle * 0.5).tan()).abs() < 1e-6);
+        assert!((arc.tangent_at(1.0) - exit_direction).magnitude() < 1e-6);
+    }
+}
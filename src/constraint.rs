@@ -0,0 +1,476 @@
+use crate::{bezier::Bezier, curve::Curve, geometry::Point};
+
+/// Number of steps along a move at which the radius is checked to find where
+/// the curve first becomes sharper than the minimum radius
+const RADIUS_SAMPLES: usize = 16;
+/// Bisection iterations used to find how far a move can go within the step
+/// where the curve becomes sharper than the minimum radius
+const RADIUS_ITERATIONS: usize = 24;
+/// Handles projected onto a fixed heading are kept at least this long so
+/// that the heading stays defined
+const MIN_HANDLE_LENGTH: f64 = 1e-3;
+/// Positions closer than this in layout units to where a constraint allows
+/// are taken to keep to it
+const LOCUS_EPSILON: f64 = 1e-6;
+
+/// One of the two ends of a curve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum End {
+    Start,
+    End,
+}
+
+impl End {
+    /// Gets the index of the end point of a curve of degree n
+    fn index(self, n: usize) -> usize {
+        match self {
+            End::Start => 0,
+            End::End => n,
+        }
+    }
+
+    /// Gets the index of the control point next to the end point, which
+    /// sets the heading at the end, of a curve of degree n
+    fn handle(self, n: usize) -> usize {
+        match self {
+            End::Start => 1,
+            End::End => n - 1,
+        }
+    }
+}
+
+/// A restriction on how the control points of a curve can be moved, such as
+/// at an end that is joined to a turnout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constraint {
+    /// The end point does not move
+    FixedEnd(End),
+    /// The direction of the curve at the end does not change. A handle is
+    /// projected onto the line of the heading.
+    FixedHeading(End),
+    /// The distance from the end point to its handle does not change. A
+    /// handle is projected onto the circle around the end point.
+    FixedHandle(End),
+    /// The radius of curvature does not drop below the radius anywhere on
+    /// the curve. A move is cut short where the curve would become sharper.
+    MinRadius(f64),
+}
+
+impl Bezier {
+    /// Moves the control point at index towards p while keeping to the
+    /// constraints, and gets the position it was moved to.
+    ///
+    /// Moving an end point of a cubic or higher degree curve moves its
+    /// handle by the same amount, so the heading and handle length at that
+    /// end are kept. Targets that break an end constraint are projected onto
+    /// the nearest allowed position, and a move that would break the minimum
+    /// radius stops at the first position along the way where the curve
+    /// would become too sharp. The radius is checked at steps along the
+    /// move, as it need not shrink steadily, so a sharper stretch shorter
+    /// than a step can be passed over.
+    ///
+    /// The handle of a quadratic sets the heading at both ends, so the
+    /// constraints of both ends apply to it together. It is moved to the
+    /// nearest position that keeps to both, or not moved at all if there is
+    /// none, such as when both headings are fixed.
+    ///
+    /// If the curve is already sharper than the minimum radius its current
+    /// radius is the limit instead, so the move stops where the curve would
+    /// get any sharper. The end constraints apply wherever the move stops.
+    pub fn move_constrained(
+        &mut self,
+        index: usize,
+        p: Point,
+        constraints: &[Constraint],
+    ) -> Point {
+        let from = self.control_points()[index];
+        let min_radius = constraints
+            .iter()
+            .filter_map(|c| match c {
+                Constraint::MinRadius(r) => Some(*r),
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        let moved = |u: f64| {
+            let target = self.constrain(index, from + (p - from) * u, constraints);
+            let mut curve = self.clone();
+            curve.place(index, target);
+            (curve, target)
+        };
+
+        let (mut curve, mut target) = moved(1.0);
+        if min_radius > 0.0 && curve.min_radius().radius < min_radius {
+            // a curve that is already too sharp may not get any sharper
+            let limit = min_radius.min(self.min_radius().radius);
+            let keeps = |u: f64| moved(u).0.min_radius().radius >= limit;
+            let (mut lo, mut hi) = (0.0, 1.0);
+            for k in 1..=RADIUS_SAMPLES {
+                let u = k as f64 / RADIUS_SAMPLES as f64;
+                if !keeps(u) {
+                    hi = u;
+                    break;
+                }
+                lo = u;
+            }
+            if lo < hi {
+                for _ in 0..RADIUS_ITERATIONS {
+                    let mid = (lo + hi) * 0.5;
+                    if keeps(mid) {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+            }
+            (curve, target) = moved(lo);
+        }
+        *self = curve;
+        target
+    }
+
+    /// Gets the nearest position to p for the control point at index that
+    /// keeps to the end constraints
+    fn constrain(&self, index: usize, p: Point, constraints: &[Constraint]) -> Point {
+        let n = self.degree();
+        let q = self.control_points();
+        let mut p = p;
+        let mut loci = Vec::new();
+        for end in [End::Start, End::End] {
+            if index == end.index(n) && constraints.contains(&Constraint::FixedEnd(end)) {
+                p = q[index];
+            }
+            if index == end.handle(n) {
+                loci.extend(self.handle_locus(end, constraints));
+            }
+        }
+        match loci[..] {
+            [locus] => locus.nearest(p),
+            [a, b] => a
+                .intersections(&b)
+                .into_iter()
+                .min_by(|x, y| x.distance(&p).total_cmp(&y.distance(&p)))
+                .unwrap_or(q[index]),
+            _ => p,
+        }
+    }
+
+    /// Gets the positions the handle at the end can take under the
+    /// constraints, or None if it is free to move
+    fn handle_locus(&self, end: End, constraints: &[Constraint]) -> Option<Locus> {
+        let n = self.degree();
+        let q = self.control_points();
+        let origin = q[end.index(n)];
+        let heading = self.heading_into(end);
+        let radius = q[end.handle(n)].distance(&origin);
+        let fixed_heading = constraints.contains(&Constraint::FixedHeading(end));
+        let fixed_handle = constraints.contains(&Constraint::FixedHandle(end));
+        match (fixed_heading, fixed_handle) {
+            (true, true) => Some(Locus::Point(origin + heading * radius)),
+            (true, false) => Some(Locus::Ray { origin, heading }),
+            (false, true) => Some(Locus::Circle {
+                center: origin,
+                radius,
+                heading,
+            }),
+            (false, false) => None,
+        }
+    }
+
+    /// Gets the unit vector from the end point into the curve
+    fn heading_into(&self, end: End) -> Point {
+        match end {
+            End::Start => self.tangent_at(0.0),
+            End::End => -self.tangent_at(1.0),
+        }
+    }
+
+    /// Moves the control point at index to p, along with the handle of an
+    /// end point of a cubic or higher degree curve
+    fn place(&mut self, index: usize, p: Point) {
+        let n = self.degree();
+        let delta = p - self.control_points()[index];
+        self.set_control_point(index, p);
+        if n >= 3 {
+            for end in [End::Start, End::End] {
+                if index == end.index(n) {
+                    let handle = end.handle(n);
+                    self.set_control_point(handle, self.control_points()[handle] + delta);
+                }
+            }
+        }
+    }
+}
+
+/// The positions a handle can take under the constraints at one end
+#[derive(Debug, Clone, Copy)]
+enum Locus {
+    /// Along the heading from the end point, at least the minimum handle
+    /// length out
+    Ray { origin: Point, heading: Point },
+    /// On the circle around the end point. The heading is the direction
+    /// taken from a target at the center.
+    Circle {
+        center: Point,
+        radius: f64,
+        heading: Point,
+    },
+    /// Only at the point
+    Point(Point),
+}
+
+impl Locus {
+    /// Gets the nearest allowed position to p
+    fn nearest(&self, p: Point) -> Point {
+        match *self {
+            Locus::Ray { origin, heading } => {
+                origin + heading * (p - origin).dot(&heading).max(MIN_HANDLE_LENGTH)
+            }
+            Locus::Circle {
+                center,
+                radius,
+                heading,
+            } => center + (p - center).normalize().unwrap_or(heading) * radius,
+            Locus::Point(point) => point,
+        }
+    }
+
+    /// Gets the positions allowed by both loci
+    fn intersections(&self, other: &Locus) -> Vec<Point> {
+        match (*self, *other) {
+            (Locus::Point(p), locus) | (locus, Locus::Point(p)) => {
+                if locus.nearest(p).distance(&p) <= LOCUS_EPSILON {
+                    vec![p]
+                } else {
+                    Vec::new()
+                }
+            }
+            (
+                Locus::Ray {
+                    origin: o1,
+                    heading: d1,
+                },
+                Locus::Ray {
+                    origin: o2,
+                    heading: d2,
+                },
+            ) => {
+                // o1 + d1 * a = o2 + d2 * b
+                let denominator = d1.cross(&d2);
+                if denominator.abs() <= f64::EPSILON {
+                    return Vec::new();
+                }
+                let a = (o2 - o1).cross(&d2) / denominator;
+                let b = (o2 - o1).cross(&d1) / denominator;
+                if a >= MIN_HANDLE_LENGTH && b >= MIN_HANDLE_LENGTH {
+                    vec![o1 + d1 * a]
+                } else {
+                    Vec::new()
+                }
+            }
+            (Locus::Ray { origin, heading }, Locus::Circle { center, radius, .. })
+            | (Locus::Circle { center, radius, .. }, Locus::Ray { origin, heading }) => {
+                // |origin + heading * s - center| = radius
+                let offset = origin - center;
+                let b = heading.dot(&offset);
+                let discriminant = b * b - offset.dot(&offset) + radius * radius;
+                if discriminant < 0.0 {
+                    return Vec::new();
+                }
+                let root = discriminant.sqrt();
+                [-b - root, -b + root]
+                    .into_iter()
+                    .filter(|s| *s >= MIN_HANDLE_LENGTH)
+                    .map(|s| origin + heading * s)
+                    .collect()
+            }
+            (
+                Locus::Circle {
+                    center: c1,
+                    radius: r1,
+                    ..
+                },
+                Locus::Circle {
+                    center: c2,
+                    radius: r2,
+                    ..
+                },
+            ) => {
+                let d = c1.distance(&c2);
+                if d <= f64::EPSILON || d > r1 + r2 || d < (r1 - r2).abs() {
+                    return Vec::new();
+                }
+                // distance from c1 along the line of centers to the chord
+                // through the intersections, and half the chord
+                let a = (r1 * r1 - r2 * r2 + d * d) / (2.0 * d);
+                let h = (r1 * r1 - a * a).max(0.0).sqrt();
+                let axis = (c2 - c1) * (1.0 / d);
+                let foot = c1 + axis * a;
+                vec![
+                    foot + axis.perpendicular() * h,
+                    foot - axis.perpendicular() * h,
+                ]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::{bend, bezier};
+
+    #[test]
+    fn test_fixed_end() {
+        let mut b = bend(0.1);
+        let p = b.move_constrained(
+            0,
+            Point { x: 10.0, y: 10.0 },
+            &[Constraint::FixedEnd(End::Start)],
+        );
+        assert_eq!(p, Point { x: 0.0, y: 0.0 });
+        assert_eq!(b.control_points(), bend(0.1).control_points());
+
+        // the other end moves with its handle
+        let p = b.move_constrained(
+            3,
+            Point { x: 410.0, y: 420.0 },
+            &[Constraint::FixedEnd(End::Start)],
+        );
+        assert_eq!(p, Point { x: 410.0, y: 420.0 });
+        assert_eq!(b.control_points()[2], Point { x: 410.0, y: 220.0 });
+    }
+
+    #[test]
+    fn test_fixed_heading() {
+        let mut b = bend(0.1);
+        let p = b.move_constrained(
+            1,
+            Point { x: 300.0, y: 50.0 },
+            &[Constraint::FixedHeading(End::Start)],
+        );
+        assert_eq!(p, Point { x: 300.0, y: 0.0 });
+        assert_eq!(b.tangent_at(0.0), Point { x: 1.0, y: 0.0 });
+
+        // the handle cannot be pulled through the end point
+        let p = b.move_constrained(
+            1,
+            Point { x: -50.0, y: 0.0 },
+            &[Constraint::FixedHeading(End::Start)],
+        );
+        assert!(p.x > 0.0);
+        assert_eq!(b.tangent_at(0.0), Point { x: 1.0, y: 0.0 });
+
+        // a line keeps its heading by moving along itself
+        let mut line = bezier([(0.0, 0.0), (100.0, 0.0)], 0.1);
+        let p = line.move_constrained(
+            1,
+            Point { x: 150.0, y: 20.0 },
+            &[Constraint::FixedHeading(End::Start)],
+        );
+        assert_eq!(p, Point { x: 150.0, y: 0.0 });
+    }
+
+    #[test]
+    fn test_fixed_handle() {
+        let mut b = bend(0.1);
+        let end = b.control_points()[3];
+        let length = b.control_points()[2].distance(&end);
+        let p = b.move_constrained(
+            2,
+            Point { x: 300.0, y: 400.0 },
+            &[
+                Constraint::FixedHandle(End::End),
+                Constraint::FixedEnd(End::End),
+            ],
+        );
+        assert!((p.distance(&end) - length).abs() < 1e-9);
+        assert_eq!(p, Point { x: 200.0, y: 400.0 });
+
+        let p = b.move_constrained(
+            2,
+            Point { x: 0.0, y: 0.0 },
+            &[
+                Constraint::FixedHandle(End::End),
+                Constraint::FixedHeading(End::End),
+            ],
+        );
+        assert_eq!(p, Point { x: 200.0, y: 400.0 });
+    }
+
+    #[test]
+    fn test_quadratic() {
+        let quadratic = || bezier([(0.0, 0.0), (100.0, 100.0), (200.0, 0.0)], 0.1);
+        // the handle sets both headings, so fixing both stops it moving
+        let mut b = quadratic();
+        let p = b.move_constrained(
+            1,
+            Point { x: 150.0, y: 150.0 },
+            &[
+                Constraint::FixedHeading(End::Start),
+                Constraint::FixedHeading(End::End),
+            ],
+        );
+        assert_eq!(p, Point { x: 100.0, y: 100.0 });
+        assert_eq!(b.control_points(), quadratic().control_points());
+
+        // fixed handles at both ends allow the mirror image across the chord
+        let p = b.move_constrained(
+            1,
+            Point { x: 90.0, y: -120.0 },
+            &[
+                Constraint::FixedHandle(End::Start),
+                Constraint::FixedHandle(End::End),
+            ],
+        );
+        assert!(
+            p.distance(&Point {
+                x: 100.0,
+                y: -100.0
+            }) < 1e-9
+        );
+
+        // a fixed heading at one end alone still slides the handle along it
+        let mut b = quadratic();
+        let p = b.move_constrained(
+            1,
+            Point { x: 150.0, y: 130.0 },
+            &[Constraint::FixedHeading(End::Start)],
+        );
+        assert!(p.distance(&Point { x: 140.0, y: 140.0 }) < 1e-9);
+    }
+
+    #[test]
+    fn test_min_radius() {
+        let mut b = bend(0.1);
+        assert!(b.min_radius().radius > 150.0);
+        // pulling the handle in towards the start point sharpens the bend
+        let constraints = [
+            Constraint::FixedHeading(End::Start),
+            Constraint::MinRadius(150.0),
+        ];
+        let p = b.move_constrained(1, Point { x: 20.0, y: 0.0 }, &constraints);
+        assert_eq!(p.y, 0.0);
+        assert!(p.x > 20.0 && p.x < 200.0);
+        assert!((b.min_radius().radius - 150.0).abs() < 1.0);
+
+        // a move that keeps the radius is not cut short
+        let p = b.move_constrained(1, Point { x: 300.0, y: 0.0 }, &constraints);
+        assert_eq!(p, Point { x: 300.0, y: 0.0 });
+
+        // a curve that is already too sharp keeps to its end constraints and
+        // gets no sharper
+        let mut b = bend(0.1);
+        let current = b.min_radius().radius;
+        let constraints = [
+            Constraint::FixedHeading(End::Start),
+            Constraint::MinRadius(1000.0),
+        ];
+        let p = b.move_constrained(1, Point { x: 20.0, y: 30.0 }, &constraints);
+        assert_eq!(p, Point { x: 200.0, y: 0.0 });
+        assert!(b.min_radius().radius >= current);
+        // pulling the handle out eases the bend, so the whole move is made
+        let p = b.move_constrained(1, Point { x: 300.0, y: 30.0 }, &constraints);
+        assert_eq!(p, Point { x: 300.0, y: 0.0 });
+        assert!(b.min_radius().radius > current);
+    }
+}
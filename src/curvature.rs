@@ -0,0 +1,267 @@
+use crate::{bezier::Bezier, curve::Curve, geometry::Point};
+
+/// Number of samples used to bracket the minimum radius before refining it
+const MIN_RADIUS_SAMPLES: usize = 64;
+/// Golden section iterations used to refine the minimum radius
+const MIN_RADIUS_ITERATIONS: usize = 32;
+/// Bisection iterations used to locate where the radius crosses a limit
+const LIMIT_ITERATIONS: usize = 24;
+
+/// The curvature of a curve at a single position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurvatureSample {
+    pub t: f64,
+    /// Distance along the curve from the start
+    pub distance: f64,
+    pub point: Point,
+    /// Signed curvature, positive where the curve turns counter-clockwise,
+    /// and infinite at a cusp
+    pub curvature: f64,
+    /// Radius of curvature, infinite on straight sections and zero at a cusp
+    pub radius: f64,
+}
+
+/// The curvature of a curve sampled at regular distances along its length
+#[derive(Debug, Clone)]
+pub struct RadiusProfile {
+    samples: Vec<CurvatureSample>,
+}
+
+impl Bezier {
+    /// Gets the curvature of the curve at parameter t
+    pub fn curvature_sample(&self, t: f64) -> CurvatureSample {
+        CurvatureSample {
+            t,
+            distance: self.length_table().distance_at(t),
+            point: self.point_at(t),
+            curvature: self.curvature_at(t),
+            radius: self.radius_at(t),
+        }
+    }
+
+    /// Gets the position of the minimum radius of the curve.
+    ///
+    /// The curvature is sampled along the whole curve to find the sharpest
+    /// region, so the result is the global rather than a local minimum. The
+    /// position is then refined with a golden section search.
+    ///
+    /// The samples can step over a cusp, where the curve stops and turns
+    /// back with an infinite curvature, so the first cusp found by `analyze`
+    /// is reported with a zero radius.
+    pub fn min_radius(&self) -> CurvatureSample {
+        if let Some(&t) = self.cusps().first() {
+            return self.cusp_sample(t);
+        }
+        let k = |t: f64| self.curvature_at(t).abs();
+        let mut best = 0;
+        let mut best_k = k(0.0);
+        for i in 1..=MIN_RADIUS_SAMPLES {
+            let ki = k(i as f64 / MIN_RADIUS_SAMPLES as f64);
+            if ki > best_k {
+                best = i;
+                best_k = ki;
+            }
+        }
+        let step = 1.0 / MIN_RADIUS_SAMPLES as f64;
+        let mut lo = (best as f64 - 1.0).max(0.0) * step;
+        let mut hi = (best as f64 + 1.0).min(MIN_RADIUS_SAMPLES as f64) * step;
+        let ratio = (5.0_f64.sqrt() - 1.0) * 0.5;
+        for _ in 0..MIN_RADIUS_ITERATIONS {
+            let a = hi - (hi - lo) * ratio;
+            let b = lo + (hi - lo) * ratio;
+            if k(a) > k(b) {
+                hi = b;
+            } else {
+                lo = a;
+            }
+        }
+        let t = (lo + hi) * 0.5;
+        // the refined position can only replace the sample if it is sharper
+        if k(t) >= best_k {
+            self.curvature_sample(t)
+        } else {
+            self.curvature_sample(best as f64 * step)
+        }
+    }
+
+    /// Gets the radius profile of the curve sampled at roughly the spacing
+    /// in layout units. The first and last samples are the curve ends, and
+    /// any cusps of the curve are sampled as well.
+    pub fn radius_profile(&self, spacing: f64) -> RadiusProfile {
+        let table = self.length_table();
+        let length = table.length();
+        let count = (length / spacing).ceil().max(1.0) as usize;
+        let mut samples = (0..=count)
+            .map(|i| {
+                let distance = length * i as f64 / count as f64;
+                let t = table.t_at_distance(distance);
+                CurvatureSample {
+                    t,
+                    distance,
+                    point: self.point_at(t),
+                    curvature: self.curvature_at(t),
+                    radius: self.radius_at(t),
+                }
+            })
+            .collect::<Vec<_>>();
+        for t in self.cusps() {
+            let sample = self.cusp_sample(t);
+            let i = samples.partition_point(|s| s.t < t);
+            samples.insert(i, sample);
+        }
+        RadiusProfile { samples }
+    }
+
+    /// Gets the ranges of t, start to end, where the radius of the curve is
+    /// below the limit.
+    pub fn radius_below(&self, limit: f64) -> Vec<(f64, f64)> {
+        let below = |t: f64| self.radius_at(t) < limit;
+        let crossing = |lo: f64, hi: f64| {
+            let (mut lo, mut hi) = (lo, hi);
+            let start = below(lo);
+            for _ in 0..LIMIT_ITERATIONS {
+                let mid = (lo + hi) * 0.5;
+                if below(mid) == start {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            (lo + hi) * 0.5
+        };
+        let mut ranges = Vec::new();
+        let mut start = if below(0.0) { Some(0.0) } else { None };
+        let mut t0 = 0.0;
+        for i in 1..=MIN_RADIUS_SAMPLES {
+            let t1 = i as f64 / MIN_RADIUS_SAMPLES as f64;
+            match (start, below(t1)) {
+                (None, true) => start = Some(crossing(t0, t1)),
+                (Some(s), false) => {
+                    ranges.push((s, crossing(t0, t1)));
+                    start = None;
+                }
+                _ => {}
+            }
+            t0 = t1;
+        }
+        if let Some(s) = start {
+            ranges.push((s, 1.0));
+        }
+        ranges
+    }
+
+    /// Gets the parameters of the cusps found by `analyze`
+    fn cusps(&self) -> Vec<f64> {
+        self.analyze().map(|a| a.cusps).unwrap_or_default()
+    }
+
+    /// Gets the curvature at a cusp, which is infinite even where the
+    /// parameter of the cusp is not exactly where the curve is stationary.
+    fn cusp_sample(&self, t: f64) -> CurvatureSample {
+        CurvatureSample {
+            curvature: f64::INFINITY,
+            radius: 0.0,
+            ..self.curvature_sample(t)
+        }
+    }
+}
+
+impl RadiusProfile {
+    pub fn samples(&self) -> &[CurvatureSample] {
+        &self.samples
+    }
+
+    /// Gets the sample with the smallest radius
+    pub fn min_radius(&self) -> &CurvatureSample {
+        self.samples
+            .iter()
+            .min_by(|a, b| a.radius.total_cmp(&b.radius))
+            .unwrap()
+    }
+
+    /// Gets the samples with a radius below the limit
+    pub fn below(&self, limit: f64) -> Vec<&CurvatureSample> {
+        self.samples.iter().filter(|s| s.radius < limit).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::{bezier, quarter_circle};
+
+    #[test]
+    fn test_curvature_at() {
+        let b = quarter_circle(0.25);
+        // the cubic approximation of a circle varies in radius by about 2%
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            assert!((b.radius_at(t) - 100.0).abs() < 3.0);
+            assert!(b.curvature_at(t) > 0.0);
+        }
+        let line = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 1.0, y: 0.0 },
+                Point { x: 2.0, y: 0.0 },
+                Point { x: 3.0, y: 0.0 },
+            ],
+            0.25,
+        );
+        assert_eq!(line.curvature_at(0.5), 0.0);
+        assert_eq!(line.radius_at(0.5), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_min_radius() {
+        // symmetric curve that is sharpest in the middle
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+                Point { x: 100.0, y: 100.0 },
+            ],
+            0.25,
+        );
+        let min = b.min_radius();
+        assert!((min.t - 0.5).abs() < 0.001);
+        assert!(min.radius < b.radius_at(0.4));
+        assert!(min.radius < b.radius_at(0.6));
+        assert_eq!(min.point, b.point_at(min.t));
+        // distances come from the cached table and map back to the same point
+        let at = b.point_at_distance(b.length_table(), min.distance);
+        assert!(at.distance(&min.point) < 1e-6);
+
+        let profile = b.radius_profile(5.0);
+        assert_eq!(profile.samples()[0].distance, 0.0);
+        assert!(profile.min_radius().radius >= min.radius);
+        assert!(!profile.below(min.radius * 2.0).is_empty());
+
+        let ranges = b.radius_below(min.radius * 2.0);
+        assert_eq!(ranges.len(), 1);
+        assert!(ranges[0].0 < min.t && ranges[0].1 > min.t);
+        assert!(b.radius_below(min.radius * 0.5).is_empty());
+
+        // a curve that turns back on itself has no radius at the cusp
+        let cusp = bezier(
+            [(0.0, 0.0), (100.0, 100.0), (0.0, 100.0), (100.0, 0.0)],
+            0.25,
+        );
+        let min = cusp.min_radius();
+        assert!((min.t - 0.5).abs() < 0.001);
+        assert_eq!(min.radius, 0.0);
+        assert_eq!(min.curvature, f64::INFINITY);
+        assert_eq!(cusp.curvature_at(0.5), f64::INFINITY);
+        assert_eq!(cusp.radius_at(0.5), 0.0);
+
+        let profile = cusp.radius_profile(5.0);
+        assert_eq!(profile.min_radius().radius, 0.0);
+        assert!((profile.min_radius().t - 0.5).abs() < 0.001);
+        assert_eq!(profile.below(1.0).len(), 1);
+
+        let ranges = cusp.radius_below(1.0);
+        assert_eq!(ranges.len(), 1);
+        assert!(ranges[0].0 < 0.5 && ranges[0].1 > 0.5);
+    }
+}
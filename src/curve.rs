@@ -0,0 +1,298 @@
+use crate::geometry::Point;
+
+/// Derivative vectors with a magnitude below this value are treated as zero
+/// length when computing tangents.
+pub(crate) const DEGENERATE_EPSILON: f64 = 1e-5;
+/// Arc length table entries never span more than this range of t so that the
+/// inverse mapping stays well conditioned on long, gentle curves.
+const ARC_LENGTH_MAX_STEP: f64 = 0.0625;
+/// Limits the subdivision depth of the arc length table.
+const ARC_LENGTH_MAX_DEPTH: u32 = 16;
+/// Abscissae and weights of 5 point Gauss-Legendre quadrature on [-1, 1].
+const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+    (0.0, 0.568_888_888_888_888_9),
+    (-0.538_469_310_105_683, 0.478_628_670_499_366_5),
+    (0.538_469_310_105_683, 0.478_628_670_499_366_5),
+    (-0.906_179_845_938_664, 0.236_926_885_056_189_1),
+    (0.906_179_845_938_664, 0.236_926_885_056_189_1),
+];
+
+/// A position on a curve along with the unit tangent and unit left normal
+/// of the curve at that position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub t: f64,
+    pub point: Point,
+    pub tangent: Point,
+    pub normal: Point,
+}
+
+/// Maps between the distance along a curve and the curve parameter t.
+///
+/// The table holds the cumulative arc length at adaptively chosen values of
+/// t together with the speed |C'(t)| of the curve at each entry. Lookups
+/// between entries use cubic Hermite interpolation of t(s), whose slope is
+/// dt/ds = 1 / |C'(t)|.
+#[derive(Debug, Clone)]
+pub struct ArcLengthTable {
+    t: Vec<f64>,
+    distance: Vec<f64>,
+    speed: Vec<f64>,
+}
+
+/// A parametric curve C(t) defined for 0.0 <= t <= 1.0.
+///
+/// Implementations provide the position and derivatives of the curve and how
+/// to flatten it. The tangents, curvature, arc length and distance based
+/// lookups are derived from those.
+pub trait Curve {
+    /// Gets the point on the curve at parameter t where 0.0 <= t <= 1.0.
+    /// Values of t outside of the range are clamped.
+    fn point_at(&self, t: f64) -> Point;
+
+    /// Gets the first derivative of the curve at parameter t. The result is
+    /// the (unnormalized) tangent vector of the curve at t.
+    fn derivative_at(&self, t: f64) -> Point;
+
+    /// Gets the second derivative of the curve at parameter t.
+    fn second_derivative_at(&self, t: f64) -> Point;
+
+    /// Gets the parameters of the vertices of a polyline that stays within
+    /// the curve tolerance of the curve, starting at t = 0.0 and ending at
+    /// t = 1.0.
+    fn flatten(&self) -> Vec<f64>;
+
+    /// Gets the maximum distance in layout units between the curve and the
+    /// polyline used to render and measure it.
+    fn tolerance(&self) -> f64;
+
+    /// Gets the unit tangent of the curve at parameter t.
+    ///
+    /// Where the first derivative vanishes the direction of the second
+    /// derivative is used, since near a stationary point C'(t + h) is
+    /// approximately h * C''(t). A curve with no direction there falls back
+    /// to the chord and then to the x axis.
+    fn tangent_at(&self, t: f64) -> Point {
+        let t = t.clamp(0.0, 1.0);
+        if let Some(tangent) = direction(self.derivative_at(t)) {
+            return tangent;
+        }
+        let sign = if t < 1.0 { 1.0 } else { -1.0 };
+        direction(self.second_derivative_at(t) * sign)
+            .or_else(|| direction(self.point_at(1.0) - self.point_at(0.0)))
+            .unwrap_or(Point { x: 1.0, y: 0.0 })
+    }
+
+    /// Gets the unit left normal of the curve at parameter t. The normal is
+    /// the tangent rotated 90 degrees counter-clockwise.
+    fn normal_at(&self, t: f64) -> Point {
+        self.tangent_at(t).perpendicular()
+    }
+
+    /// Gets the signed curvature of the curve at parameter t
+    ///
+    ///          C'(t) x C''(t)
+    ///      k = --------------
+    ///            |C'(t)|^3
+    /// The curvature is positive where the curve turns counter-clockwise and
+    /// is infinite where the curve is stationary, such as at a cusp.
+    fn curvature_at(&self, t: f64) -> f64 {
+        let d1 = self.derivative_at(t);
+        let d2 = self.second_derivative_at(t);
+        let speed = d1.magnitude();
+        if speed <= DEGENERATE_EPSILON {
+            return f64::INFINITY;
+        }
+        d1.cross(&d2) / (speed * speed * speed)
+    }
+
+    /// Gets the radius of curvature of the curve at parameter t. Straight
+    /// sections have an infinite radius and stationary points, such as a
+    /// cusp, have a zero radius.
+    fn radius_at(&self, t: f64) -> f64 {
+        let k = self.curvature_at(t).abs();
+        if k == 0.0 {
+            return f64::INFINITY;
+        }
+        1.0 / k
+    }
+
+    /// Gets the position, unit tangent and unit normal of the curve at
+    /// parameter t.
+    fn frame_at(&self, t: f64) -> Frame {
+        let tangent = self.tangent_at(t);
+        Frame {
+            t,
+            point: self.point_at(t),
+            tangent,
+            normal: tangent.perpendicular(),
+        }
+    }
+
+    /// Gets the frames of the curve at the vertices of the flattened curve.
+    /// The first and last frames are always at t = 0.0 and t = 1.0.
+    fn frames(&self) -> Vec<Frame> {
+        self.flatten()
+            .into_iter()
+            .map(|t| self.frame_at(t))
+            .collect()
+    }
+
+    /// Gets the vertices of the flattened curve
+    fn polyline(&self) -> Vec<Point> {
+        self.flatten()
+            .into_iter()
+            .map(|t| self.point_at(t))
+            .collect()
+    }
+
+    /// Gets the arc length of the curve between the parameters t0 and t1
+    /// using Gauss-Legendre quadrature of the curve speed.
+    fn arc_length(&self, t0: f64, t1: f64) -> f64 {
+        let half = (t1 - t0) * 0.5;
+        let mid = (t1 + t0) * 0.5;
+        GAUSS_LEGENDRE
+            .iter()
+            .map(|(x, w)| w * self.derivative_at(mid + half * x).magnitude())
+            .sum::<f64>()
+            * half
+    }
+
+    /// Gets the total arc length of the curve
+    fn length(&self) -> f64 {
+        self.arc_length(0.0, 1.0)
+    }
+
+    /// Creates the arc length table of the curve. The tolerance is the
+    /// maximum error in layout units of the total length of the curve.
+    fn arc_length_table(&self, tolerance: f64) -> ArcLengthTable {
+        let mut table = ArcLengthTable {
+            t: vec![0.0],
+            distance: vec![0.0],
+            speed: vec![self.derivative_at(0.0).magnitude()],
+        };
+        let length = self.arc_length(0.0, 1.0);
+        subdivide_arc_length(self, 0.0, 1.0, length, tolerance, 0, &mut table);
+        table
+    }
+
+    /// Gets the point at the specified distance along the curve
+    fn point_at_distance(&self, table: &ArcLengthTable, distance: f64) -> Point {
+        self.point_at(table.t_at_distance(distance))
+    }
+
+    /// Gets the unit tangent at the specified distance along the curve
+    fn tangent_at_distance(&self, table: &ArcLengthTable, distance: f64) -> Point {
+        self.tangent_at(table.t_at_distance(distance))
+    }
+
+    /// Gets the frame at the specified distance along the curve
+    fn frame_at_distance(&self, table: &ArcLengthTable, distance: f64) -> Frame {
+        self.frame_at(table.t_at_distance(distance))
+    }
+}
+
+fn subdivide_arc_length<C: Curve + ?Sized>(
+    curve: &C,
+    t0: f64,
+    t1: f64,
+    length: f64,
+    tolerance: f64,
+    depth: u32,
+    table: &mut ArcLengthTable,
+) {
+    let mid = (t0 + t1) * 0.5;
+    let left = curve.arc_length(t0, mid);
+    let right = curve.arc_length(mid, t1);
+    let error = (left + right - length).abs();
+    if depth < ARC_LENGTH_MAX_DEPTH
+        && (t1 - t0 > ARC_LENGTH_MAX_STEP || error > tolerance * (t1 - t0))
+    {
+        subdivide_arc_length(curve, t0, mid, left, tolerance, depth + 1, table);
+        subdivide_arc_length(curve, mid, t1, right, tolerance, depth + 1, table);
+    } else {
+        let distance = table.distance.last().unwrap() + left + right;
+        table.t.push(t1);
+        table.distance.push(distance);
+        table.speed.push(curve.derivative_at(t1).magnitude());
+    }
+}
+
+impl ArcLengthTable {
+    /// Gets the total length of the curve
+    pub fn length(&self) -> f64 {
+        *self.distance.last().unwrap()
+    }
+
+    /// Gets the distance along the curve at parameter t
+    pub fn distance_at(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        let i = segment(&self.t, t);
+        let span = self.t[i + 1] - self.t[i];
+        let u = if span > 0.0 {
+            (t - self.t[i]) / span
+        } else {
+            0.0
+        };
+        hermite(
+            self.distance[i],
+            self.distance[i + 1],
+            self.speed[i] * span,
+            self.speed[i + 1] * span,
+            u,
+        )
+    }
+
+    /// Gets the curve parameter t at the specified distance along the curve.
+    /// Distances outside of the curve are clamped to the curve ends.
+    pub fn t_at_distance(&self, distance: f64) -> f64 {
+        let distance = distance.clamp(0.0, self.length());
+        let i = segment(&self.distance, distance);
+        let span = self.distance[i + 1] - self.distance[i];
+        if span <= 0.0 {
+            return self.t[i];
+        }
+        let u = (distance - self.distance[i]) / span;
+        let dt = self.t[i + 1] - self.t[i];
+        // fall back to linear interpolation where the curve is stationary
+        if self.speed[i] <= DEGENERATE_EPSILON || self.speed[i + 1] <= DEGENERATE_EPSILON {
+            return self.t[i] + dt * u;
+        }
+        let t = hermite(
+            self.t[i],
+            self.t[i + 1],
+            span / self.speed[i],
+            span / self.speed[i + 1],
+            u,
+        );
+        t.clamp(self.t[i], self.t[i + 1])
+    }
+}
+
+/// Gets the index of the table segment containing value
+fn segment(values: &[f64], value: f64) -> usize {
+    match values.binary_search_by(|v| v.total_cmp(&value)) {
+        Ok(i) => i.min(values.len() - 2),
+        Err(i) => i.clamp(1, values.len() - 1) - 1,
+    }
+}
+
+/// Cubic Hermite interpolation between p0 and p1 with the end slopes m0 and
+/// m1 scaled to the unit interval.
+fn hermite(p0: f64, p1: f64, m0: f64, m1: f64, u: f64) -> f64 {
+    let u2 = u * u;
+    let u3 = u2 * u;
+    (2.0 * u3 - 3.0 * u2 + 1.0) * p0
+        + (u3 - 2.0 * u2 + u) * m0
+        + (-2.0 * u3 + 3.0 * u2) * p1
+        + (u3 - u2) * m1
+}
+
+/// Gets the unit vector of v unless v is too short to have a reliable
+/// direction.
+pub(crate) fn direction(v: Point) -> Option<Point> {
+    if v.magnitude() <= DEGENERATE_EPSILON {
+        return None;
+    }
+    v.normalize()
+}
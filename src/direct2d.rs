@@ -1,5 +1,7 @@
 use std::ptr::null;
 
+use crate::geometry::Point;
+
 use windows::{
     core::*,
     Foundation::Numerics::Matrix3x2,
@@ -106,3 +108,41 @@ pub fn load_bitmap(
         target.CreateBitmapFromWicBitmap(&converter, None)
     }
 }
+
+/// Direct2D draws in single precision, so layout points are rounded to f32
+/// where they are passed to it.
+impl From<Point> for D2D_POINT_2F {
+    fn from(p: Point) -> Self {
+        D2D_POINT_2F {
+            x: p.x as f32,
+            y: p.y as f32,
+        }
+    }
+}
+
+impl From<&Point> for D2D_POINT_2F {
+    fn from(p: &Point) -> Self {
+        D2D_POINT_2F {
+            x: p.x as f32,
+            y: p.y as f32,
+        }
+    }
+}
+
+impl From<D2D_POINT_2F> for Point {
+    fn from(p: D2D_POINT_2F) -> Self {
+        Point {
+            x: p.x as f64,
+            y: p.y as f64,
+        }
+    }
+}
+
+impl From<&D2D_POINT_2F> for Point {
+    fn from(p: &D2D_POINT_2F) -> Self {
+        Point {
+            x: p.x as f64,
+            y: p.y as f64,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{bezier::Bezier, geometry::Pose, path::BezierPath};
+
+/// Handles are never shorter than this fraction of the chord, where the
+/// curvature at the ends grows without bound
+const MIN_HANDLE_FACTOR: f64 = 0.01;
+/// The pattern search stops once its step is smaller than this fraction of
+/// the tolerance
+const SEARCH_STEP_FACTOR: f64 = 0.1;
+/// Limits the number of pattern search steps
+const SEARCH_MAX_ITERATIONS: usize = 256;
+/// Directions of the pattern search steps in the handle lengths. The
+/// diagonals follow the ridge where the curvature peaks at two places at
+/// once.
+const SEARCH_DIRECTIONS: [(f64, f64); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (1.0, 1.0),
+    (-1.0, -1.0),
+    (1.0, -1.0),
+    (-1.0, 1.0),
+];
+
+impl BezierPath {
+    /// Creates the approximate elastica between the start and end poses,
+    /// the shape that flex track settles into when only its ends are held.
+    /// Gets None if the poses are at the same point.
+    ///
+    /// Bent track stores the bending energy
+    ///      E = ∫ k(s)^2 ds
+    /// and comes to rest where that energy is smallest, which spreads the
+    /// bend along the track and keeps the peak curvature low. The shape is
+    /// approximated with the cubic whose control points lie along the
+    /// headings at the handle lengths a and b:
+    ///      P1 = P0 + a * D0,  P2 = P3 - b * D3
+    /// where a and b give the smallest peak curvature. They are found with
+    /// a compass search, which tries a step either way in each handle
+    /// length and in both together, moves to any improvement and halves the
+    /// step when there is none. Curves that loop or fold back on themselves
+    /// are never chosen.
+    ///
+    /// The cubic is split at its inflections so that each segment of the
+    /// path turns one way.
+    ///
+    /// A single cubic cannot follow a circle exactly, so through a U-turn
+    /// the curvature peaks about 5% above that of the semicircle between
+    /// the same poses.
+    pub fn elastica(start: &Pose, end: &Pose, tolerance: f64) -> Option<BezierPath> {
+        let chord = start.point.distance(&end.point);
+        if chord <= f64::EPSILON {
+            return None;
+        }
+        let min_handle = chord * MIN_HANDLE_FACTOR;
+        let peak = |a: f64, b: f64| {
+            if a < min_handle || b < min_handle {
+                return f64::INFINITY;
+            }
+            let curve = Bezier::between_poses(start, end, a, b, tolerance);
+            if !curve.is_buildable() {
+                return f64::INFINITY;
+            }
+            curve.min_radius().curvature.abs()
+        };
+
+        let (mut a, mut b) = (chord / 3.0, chord / 3.0);
+        let mut best = peak(a, b);
+        let mut step = chord / 6.0;
+        for _ in 0..SEARCH_MAX_ITERATIONS {
+            if step < tolerance * SEARCH_STEP_FACTOR {
+                break;
+            }
+            let improvement = SEARCH_DIRECTIONS
+                .iter()
+                .map(|(da, db)| (da * step, db * step))
+                .map(|(da, db)| (a + da, b + db))
+                .map(|(a, b)| (a, b, peak(a, b)))
+                .filter(|(_, _, e)| *e < best)
+                .min_by(|x, y| x.2.total_cmp(&y.2));
+            match improvement {
+                Some((na, nb, e)) => {
+                    (a, b, best) = (na, nb, e);
+                }
+                None => step *= 0.5,
+            }
+        }
+
+        let curve = Bezier::between_poses(start, end, a, b, tolerance);
+        let mut bounds = vec![0.0];
+        if let Some(analysis) = curve.analyze() {
+            bounds.extend(analysis.inflections);
+        }
+        bounds.push(1.0);
+        let mut parts = bounds.windows(2).map(|w| curve.subcurve(w[0], w[1]));
+        let mut path = BezierPath::new(parts.next()?);
+        for part in parts {
+            path.push(part);
+        }
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{curve::Curve, fixtures::quarter_turn, geometry::Point};
+    use std::f64::consts::PI;
+
+    fn peak_curvature(path: &BezierPath) -> f64 {
+        path.segments()
+            .iter()
+            .map(|s| s.min_radius().curvature.abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_quarter_turn() {
+        let (start, end) = quarter_turn();
+        let path = BezierPath::elastica(&start, &end, 0.01).unwrap();
+        assert_eq!(path.segment_count(), 1);
+        let curve = &path.segments()[0];
+        assert_eq!(curve.point_at(0.0), start.point);
+        assert_eq!(curve.point_at(1.0), end.point);
+        assert!((curve.tangent_at(0.0) - start.direction()).magnitude() < 1e-9);
+        assert!((curve.tangent_at(1.0) - end.direction()).magnitude() < 1e-9);
+        // the symmetric poses give equal handles
+        let p = curve.control_points();
+        assert!((p[1].distance(&p[0]) - p[2].distance(&p[3])).abs() < 0.1);
+
+        // the track bends less than with a third of the chord for handles,
+        // and about as little as the quarter circle of radius 500
+        let chord = start.point.distance(&end.point);
+        let default = Bezier::between_poses(&start, &end, chord / 3.0, chord / 3.0, 0.01);
+        assert!(peak_curvature(&path) < default.min_radius().curvature.abs());
+        assert!(peak_curvature(&path) < 1.01 / 500.0);
+    }
+
+    #[test]
+    fn test_s_curve() {
+        let start = Pose::new(Point { x: 0.0, y: 0.0 }, 0.0);
+        let end = Pose::new(Point { x: 800.0, y: 100.0 }, 0.0);
+        let path = BezierPath::elastica(&start, &end, 0.01).unwrap();
+        assert_eq!(path.segment_count(), 2);
+        assert!(path.segments()[0].curvature_at(0.5) > 0.0);
+        assert!(path.segments()[1].curvature_at(0.5) < 0.0);
+        assert_eq!(path.reverse_curves(0.0).len(), 1);
+        assert!(path.point_at(1.0).distance(&end.point) < 1e-9);
+    }
+
+    #[test]
+    fn test_u_turn() {
+        // a single cubic peaks a little sharper than the semicircle of
+        // radius 200
+        let start = Pose::new(Point { x: 0.0, y: 0.0 }, 0.0);
+        let end = Pose::new(Point { x: 0.0, y: 400.0 }, PI);
+        let path = BezierPath::elastica(&start, &end, 0.01).unwrap();
+        assert_eq!(path.segment_count(), 1);
+        let curve = &path.segments()[0];
+        assert!(curve.is_buildable());
+        assert!((curve.tangent_at(1.0) - end.direction()).magnitude() < 1e-9);
+        assert!(peak_curvature(&path) < 1.06 / 200.0);
+        assert!(path.length() > 200.0 * PI);
+    }
+
+    #[test]
+    fn test_straight() {
+        let start = Pose::new(Point { x: 0.0, y: 0.0 }, 0.0);
+        let end = Pose::new(Point { x: 300.0, y: 0.0 }, 0.0);
+        let path = BezierPath::elastica(&start, &end, 0.01).unwrap();
+        assert!(peak_curvature(&path) < 1e-9);
+        assert!(BezierPath::elastica(&start, &start, 0.01).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    bezier::{bernstein_basis, Bezier},
+    curve::Curve,
+    geometry::Point,
+    path::BezierPath,
+    projection::refine_nearest,
+};
+
+/// Number of times the parameters of the points are improved before the
+/// points are split between two curves
+const FIT_MAX_ITERATIONS: usize = 4;
+/// Curves with a larger error than this multiple of the allowed error are
+/// split straight away rather than reparameterized
+const REPARAMETERIZE_FACTOR: f64 = 4.0;
+
+impl BezierPath {
+    /// Fits a path of cubic bezier curves to the points so that every point
+    /// is within error layout units of the path. The curves of the path are
+    /// created with the tolerance. Gets None if there are fewer than 2
+    /// distinct points.
+    ///
+    /// Uses the least squares method of Philip J. Schneider, "An Algorithm
+    /// for Automatically Fitting Digitized Curves", Graphics Gems, 1990.
+    /// The points are parameterized by chord length and a cubic with fixed
+    /// end points and end tangent directions is fitted to them. If the
+    /// error is too large the parameters are improved with Newton
+    /// iterations, and failing that the points are split at the worst fit
+    /// with a shared tangent so that the joint is G1 continuous.
+    pub fn fit(points: &[Point], error: f64, tolerance: f64) -> Option<BezierPath> {
+        let mut points = points.to_vec();
+        points.dedup_by(|a, b| a.distance(b) <= f64::EPSILON);
+        if points.len() < 2 {
+            return None;
+        }
+        let last = points.len() - 1;
+        let start_tangent = (points[1] - points[0]).normalize()?;
+        let end_tangent = (points[last - 1] - points[last]).normalize()?;
+        let mut curves = Vec::new();
+        fit_cubic(
+            &points,
+            start_tangent,
+            end_tangent,
+            error,
+            tolerance,
+            &mut curves,
+        );
+        let mut curves = curves.into_iter();
+        let mut path = BezierPath::new(curves.next()?);
+        for curve in curves {
+            path.push(curve);
+        }
+        Some(path)
+    }
+}
+
+/// Appends the cubics fitted to the points to curves. The start tangent
+/// points into the curve from the first point and the end tangent points
+/// into the curve from the last point.
+fn fit_cubic(
+    points: &[Point],
+    start_tangent: Point,
+    end_tangent: Point,
+    error: f64,
+    tolerance: f64,
+    curves: &mut Vec<Bezier>,
+) {
+    let last = points.len() - 1;
+    if points.len() == 2 {
+        let reach = points[0].distance(&points[1]) / 3.0;
+        curves.push(Bezier::new_with_ctrl_point(
+            [
+                points[0],
+                points[0] + start_tangent * reach,
+                points[1] + end_tangent * reach,
+                points[1],
+            ],
+            tolerance,
+        ));
+        return;
+    }
+
+    let mut u = chord_length_parameters(points);
+    let mut curve = least_squares_cubic(points, &u, start_tangent, end_tangent, tolerance);
+    let (mut max_error, mut split) = fit_error(points, &curve, &u);
+    if max_error <= error {
+        curves.push(curve);
+        return;
+    }
+    if max_error <= error * REPARAMETERIZE_FACTOR {
+        for _ in 0..FIT_MAX_ITERATIONS {
+            for (u, p) in u.iter_mut().zip(points) {
+                *u = refine_nearest(&curve, p, *u, 0.0, 1.0);
+            }
+            curve = least_squares_cubic(points, &u, start_tangent, end_tangent, tolerance);
+            (max_error, split) = fit_error(points, &curve, &u);
+            if max_error <= error {
+                curves.push(curve);
+                return;
+            }
+        }
+    }
+
+    // split at the worst point with the tangent of its neighbours
+    let center = (points[split - 1] - points[split + 1])
+        .normalize()
+        .or_else(|| (points[split - 1] - points[split]).normalize())
+        .unwrap_or(start_tangent);
+    fit_cubic(
+        &points[..=split],
+        start_tangent,
+        center,
+        error,
+        tolerance,
+        curves,
+    );
+    fit_cubic(
+        &points[split..=last],
+        -center,
+        end_tangent,
+        error,
+        tolerance,
+        curves,
+    );
+}
+
+/// Gets the parameters of the points proportional to the distance along the
+/// polyline through them.
+fn chord_length_parameters(points: &[Point]) -> Vec<f64> {
+    let mut u = vec![0.0];
+    for pair in points.windows(2) {
+        u.push(u.last().unwrap() + pair[0].distance(&pair[1]));
+    }
+    let length = *u.last().unwrap();
+    u.iter().map(|d| d / length).collect()
+}
+
+/// Gets the cubic through the first and last points with the end tangent
+/// directions that is closest to the points at the parameters u.
+///
+/// Only the lengths of the two handles are unknown, so the normal
+/// equations of the least squares problem are a 2 x 2 linear system. When
+/// it is singular or gives handles that point the wrong way the handles
+/// fall back to a third of the distance between the ends.
+fn least_squares_cubic(
+    points: &[Point],
+    u: &[f64],
+    start_tangent: Point,
+    end_tangent: Point,
+    tolerance: f64,
+) -> Bezier {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let mut c = [[0.0; 2]; 2];
+    let mut x = [0.0; 2];
+    for (p, u) in points.iter().zip(u) {
+        let a = [
+            start_tangent * bernstein_basis(3, 1, *u),
+            end_tangent * bernstein_basis(3, 2, *u),
+        ];
+        let tmp = *p
+            - (first * (bernstein_basis(3, 0, *u) + bernstein_basis(3, 1, *u))
+                + last * (bernstein_basis(3, 2, *u) + bernstein_basis(3, 3, *u)));
+        c[0][0] += a[0].dot(&a[0]);
+        c[0][1] += a[0].dot(&a[1]);
+        c[1][1] += a[1].dot(&a[1]);
+        x[0] += a[0].dot(&tmp);
+        x[1] += a[1].dot(&tmp);
+    }
+    c[1][0] = c[0][1];
+
+    let det = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let chord = first.distance(&last);
+    let epsilon = chord * 1e-6;
+    let (mut alpha_start, mut alpha_end) = if det.abs() > f64::EPSILON {
+        (
+            (x[0] * c[1][1] - x[1] * c[0][1]) / det,
+            (c[0][0] * x[1] - c[1][0] * x[0]) / det,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+    if alpha_start < epsilon || alpha_end < epsilon {
+        alpha_start = chord / 3.0;
+        alpha_end = chord / 3.0;
+    }
+    Bezier::new_with_ctrl_point(
+        [
+            first,
+            first + start_tangent * alpha_start,
+            last + end_tangent * alpha_end,
+            last,
+        ],
+        tolerance,
+    )
+}
+
+/// Gets the largest distance between a point and the curve at its
+/// parameter, and the index of that point. The end points are exact.
+fn fit_error(points: &[Point], curve: &Bezier, u: &[f64]) -> (f64, usize) {
+    let mut worst = (0.0, points.len() / 2);
+    for i in 1..points.len() - 1 {
+        let d = curve.point_at(u[i]).distance(&points[i]);
+        if d > worst.0 {
+            worst = (d, i);
+        }
+    }
+    worst
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::distance_to_curves;
+
+    #[test]
+    fn test_fit_arc() {
+        // points on a 22 inch radius quarter circle in millimetres
+        let radius = 558.8;
+        let points: Vec<Point> = (0..=40)
+            .map(|i| {
+                let a = std::f64::consts::FRAC_PI_2 * i as f64 / 40.0;
+                Point {
+                    x: radius * a.cos(),
+                    y: radius * a.sin(),
+                }
+            })
+            .collect();
+        let path = BezierPath::fit(&points, 0.5, 0.1).unwrap();
+        for p in &points {
+            assert!(distance_to_curves(path.segments(), p) <= 0.5);
+        }
+        assert_eq!(path.segments()[0].control_points()[0], points[0]);
+        assert_eq!(
+            *path
+                .segments()
+                .last()
+                .unwrap()
+                .control_points()
+                .last()
+                .unwrap(),
+            points[40]
+        );
+        assert!(path.kinks().is_empty());
+    }
+
+    #[test]
+    fn test_fit_split() {
+        // an S bend with a straight in the middle needs more than one cubic
+        let mut points = Vec::new();
+        for i in 0..=20 {
+            let a = std::f64::consts::PI * i as f64 / 20.0;
+            points.push(Point {
+                x: 100.0 * a.sin(),
+                y: 100.0 - 100.0 * a.cos(),
+            });
+        }
+        for i in 1..=20 {
+            points.push(Point {
+                x: -5.0 * i as f64,
+                y: 200.0,
+            });
+        }
+        let path = BezierPath::fit(&points, 0.25, 0.1).unwrap();
+        assert!(path.segment_count() > 1);
+        for p in &points {
+            assert!(distance_to_curves(path.segments(), p) <= 0.25);
+        }
+        assert!(path.kinks().is_empty());
+    }
+
+    #[test]
+    fn test_fit_degenerate() {
+        let p = Point { x: 1.0, y: 2.0 };
+        assert!(BezierPath::fit(&[p, p, p], 0.1, 0.1).is_none());
+        let path = BezierPath::fit(&[p, Point { x: 4.0, y: 6.0 }], 0.1, 0.1).unwrap();
+        assert_eq!(path.segment_count(), 1);
+        assert!((path.length() - 5.0).abs() < 1e-4);
+    }
+}
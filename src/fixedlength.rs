@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::{bezier::Bezier, curve::Curve, geometry::Pose};
+
+/// Number of handle lengths, per length of track, that are measured to
+/// bracket the required length
+const LENGTH_SAMPLES: usize = 32;
+/// The longest handles tried are this multiple of the length of track
+const MAX_HANDLE_FACTOR: usize = 4;
+/// Limits the bisection steps once the required length is bracketed
+const LENGTH_MAX_ITERATIONS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LengthError {
+    /// The track is shorter than the straight distance between the poses,
+    /// which is the shortest length that can join them.
+    TooShort { min: f64 },
+    /// The track is longer than any curve between the poses that does not
+    /// loop or fold back on itself. max is the longest length found.
+    TooLong { max: f64 },
+    /// No curve of the length was found to within the tolerance, although
+    /// the length is within the range of the curves measured. closest is
+    /// the nearest length found.
+    NotConverged { closest: f64 },
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LengthError::TooShort { min } => {
+                write!(f, "track is too short, the gap needs at least {}", min)
+            }
+            LengthError::TooLong { max } => {
+                write!(f, "track is too long, the gap takes at most {}", max)
+            }
+            LengthError::NotConverged { closest } => {
+                write!(
+                    f,
+                    "no curve of the length found, the closest is {}",
+                    closest
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LengthError {}
+
+impl Bezier {
+    /// Creates the cubic from the start pose to the end pose whose arc
+    /// length is the required length, to within the tolerance, such as a
+    /// whole stick of flex track bridging a gap.
+    ///
+    /// The control points are placed along the start and end headings with
+    /// equal handle lengths h:
+    ///      P1 = P0 + h * D0,  P2 = P3 - h * D3
+    /// The curve is the straight chord when h is 0 and usually grows longer
+    /// as the handles grow, until it loops or folds back on itself. The
+    /// length is measured at increasing handle lengths, skipping curves
+    /// that cannot be built, and each step between buildable curves whose
+    /// lengths straddle the required length is searched by bisection until
+    /// a curve within the tolerance is found. The length is not assumed to
+    /// grow steadily with h, so every step is measured.
+    ///
+    /// Gets an error if the length is shorter than the chord or longer than
+    /// any curve that can be built with flex track, or if no curve within
+    /// the tolerance is found.
+    pub fn with_length(
+        start: &Pose,
+        end: &Pose,
+        length: f64,
+        tolerance: f64,
+    ) -> Result<Bezier, LengthError> {
+        let chord = start.point.distance(&end.point);
+        if length < chord - tolerance {
+            return Err(LengthError::TooShort { min: chord });
+        }
+        let step = length.max(chord) / LENGTH_SAMPLES as f64;
+        let mut previous = Some((0.0, chord));
+        let mut max = chord;
+        let mut closest = chord;
+        for k in 1..=LENGTH_SAMPLES * MAX_HANDLE_FACTOR {
+            let hi = step * k as f64;
+            let (curve, measured) = handles(start, end, hi, tolerance);
+            if !curve.is_buildable() {
+                previous = None;
+                continue;
+            }
+            max = max.max(measured);
+            if let Some((lo, lo_measured)) = previous {
+                let straddles = (lo_measured - length) * (measured - length) <= 0.0
+                    || (measured - length).abs() <= tolerance;
+                if straddles {
+                    match bisect_length(start, end, lo, hi, length, tolerance) {
+                        Ok(curve) => return Ok(curve),
+                        Err(nearest) => {
+                            if (nearest - length).abs() < (closest - length).abs() {
+                                closest = nearest;
+                            }
+                        }
+                    }
+                }
+            }
+            if (measured - length).abs() < (closest - length).abs() {
+                closest = measured;
+            }
+            previous = Some((hi, measured));
+        }
+        if max < length - tolerance {
+            Err(LengthError::TooLong { max })
+        } else {
+            Err(LengthError::NotConverged { closest })
+        }
+    }
+}
+
+/// Gets the cubic with the handle length between the poses and its arc
+/// length measured to the tolerance
+fn handles(start: &Pose, end: &Pose, handle: f64, tolerance: f64) -> (Bezier, f64) {
+    let curve = Bezier::between_poses(start, end, handle, handle, tolerance);
+    let length = curve.arc_length_table(tolerance).length();
+    (curve, length)
+}
+
+/// Finds the handle length between lo and hi, whose curves are either side
+/// of the length, that gives the buildable curve of the length to within
+/// the tolerance. Gets the nearest length found if the search does not
+/// converge.
+fn bisect_length(
+    start: &Pose,
+    end: &Pose,
+    lo: f64,
+    hi: f64,
+    length: f64,
+    tolerance: f64,
+) -> Result<Bezier, f64> {
+    let (mut lo, mut hi) = (lo, hi);
+    let (mut curve, mut measured) = handles(start, end, hi, tolerance);
+    // the curve may get shorter rather than longer towards hi
+    let grows = handles(start, end, lo, tolerance).1 <= measured;
+    for _ in 0..LENGTH_MAX_ITERATIONS {
+        if (measured - length).abs() <= tolerance && curve.is_buildable() {
+            return Ok(curve);
+        }
+        let mid = (lo + hi) * 0.5;
+        (curve, measured) = handles(start, end, mid, tolerance);
+        if (measured < length) == grows {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    if (measured - length).abs() <= tolerance && curve.is_buildable() {
+        Ok(curve)
+    } else {
+        Err(measured)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{fixtures::quarter_turn, geometry::Point, validity::Validity};
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    const TOLERANCE: f64 = 0.01;
+
+    fn measure(curve: &Bezier) -> f64 {
+        curve.arc_length_table(TOLERANCE * 0.1).length()
+    }
+
+    #[test]
+    fn test_curve() {
+        let (start, end) = quarter_turn();
+        // a quarter circle, and a stick of 36 inch flex
+        for length in [500.0 * FRAC_PI_2, 914.4] {
+            let curve = Bezier::with_length(&start, &end, length, TOLERANCE).unwrap();
+            assert!((measure(&curve) - length).abs() < 2.0 * TOLERANCE);
+            assert_eq!(curve.point_at(0.0), start.point);
+            assert_eq!(curve.point_at(1.0), end.point);
+            assert!((curve.tangent_at(0.0) - start.direction()).magnitude() < 1e-9);
+            assert!((curve.tangent_at(1.0) - end.direction()).magnitude() < 1e-9);
+        }
+        assert!(matches!(
+            Bezier::with_length(&start, &end, 5000.0, TOLERANCE),
+            Err(LengthError::TooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn test_straight() {
+        let start = Pose::new(Point { x: 0.0, y: 0.0 }, 0.0);
+        let end = Pose::new(Point { x: 914.4, y: 0.0 }, 0.0);
+        let curve = Bezier::with_length(&start, &end, 914.4, TOLERANCE).unwrap();
+        assert!((measure(&curve) - 914.4).abs() < 2.0 * TOLERANCE);
+
+        assert_eq!(
+            Bezier::with_length(&start, &end, 900.0, TOLERANCE).err(),
+            Some(LengthError::TooShort { min: 914.4 })
+        );
+        // straight track cannot take up any more length
+        let Err(LengthError::TooLong { max }) = Bezier::with_length(&start, &end, 950.0, TOLERANCE)
+        else {
+            panic!("expected the track to be too long");
+        };
+        assert!((max - 914.4).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_s_curve() {
+        // offset parallel tracks joined with a reverse curve
+        let start = Pose::new(Point { x: 0.0, y: 0.0 }, PI);
+        let end = Pose::new(Point { x: -800.0, y: 60.0 }, PI);
+        let curve = Bezier::with_length(&start, &end, 810.0, TOLERANCE).unwrap();
+        assert!((measure(&curve) - 810.0).abs() < 2.0 * TOLERANCE);
+        assert!(matches!(curve.validity(), Some(Validity::ReverseCurve(_))));
+    }
+}
@@ -0,0 +1,70 @@
+use std::f64::consts::FRAC_PI_2;
+
+use crate::{
+    bezier::Bezier,
+    geometry::{Point, Pose},
+    path::BezierPath,
+};
+
+/// Gets the bezier curve with the control points given as (x, y)
+pub(crate) fn bezier<const N: usize>(points: [(f64, f64); N], tolerance: f64) -> Bezier {
+    Bezier::new_with_ctrl_point(points.map(|(x, y)| Point { x, y }), tolerance)
+}
+
+/// Gets the cubic approximation of the quarter circle of radius 100 around
+/// the origin, counter-clockwise from (100, 0) to (0, 100)
+pub(crate) fn quarter_circle(tolerance: f64) -> Bezier {
+    let k = 100.0 * 0.552_284_8;
+    bezier(
+        [(100.0, 0.0), (100.0, k), (k, 100.0), (0.0, 100.0)],
+        tolerance,
+    )
+}
+
+/// Gets the symmetric cubic from (0, 0) to (100, 0) that rises to 75
+pub(crate) fn arch(tolerance: f64) -> Bezier {
+    bezier(
+        [(0.0, 0.0), (0.0, 100.0), (100.0, 100.0), (100.0, 0.0)],
+        tolerance,
+    )
+}
+
+/// Gets the cubic that turns left from heading along the x axis at the
+/// origin to heading along the y axis at (400, 400)
+pub(crate) fn bend(tolerance: f64) -> Bezier {
+    bezier(
+        [(0.0, 0.0), (200.0, 0.0), (400.0, 200.0), (400.0, 400.0)],
+        tolerance,
+    )
+}
+
+/// Gets the path of two cubics from (0, 0) to (300, 0) that rises to a
+/// level hump 100 high in the middle
+pub(crate) fn hump(tolerance: f64) -> BezierPath {
+    let mut path = BezierPath::new(bezier(
+        [(0.0, 0.0), (50.0, 0.0), (100.0, 100.0), (150.0, 100.0)],
+        tolerance,
+    ));
+    path.push(bezier(
+        [(150.0, 100.0), (200.0, 100.0), (250.0, 0.0), (300.0, 0.0)],
+        tolerance,
+    ));
+    path
+}
+
+/// Gets the poses at the ends of a left hand quarter circle of radius 500
+/// that starts at the origin heading along the x axis
+pub(crate) fn quarter_turn() -> (Pose, Pose) {
+    (
+        Pose::new(Point { x: 0.0, y: 0.0 }, 0.0),
+        Pose::new(Point { x: 500.0, y: 500.0 }, FRAC_PI_2),
+    )
+}
+
+/// Gets the distance from p to the nearest of the curves
+pub(crate) fn distance_to_curves(curves: &[Bezier], p: &Point) -> f64 {
+    curves
+        .iter()
+        .map(|c| c.distance_to(p))
+        .fold(f64::INFINITY, f64::min)
+}
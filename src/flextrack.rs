@@ -1,8 +1,190 @@
-pub struct FlexTrack {
-    tie_spacing: f32,
+use crate::{
+    bezier::Bezier,
+    geometry::{Polygon, Rect},
+    track::TrackCurve,
+};
+
+/// HO scale track dimensions in millimetres
+const DEFAULT_GAUGE: f64 = 16.5;
+const DEFAULT_RAIL_WIDTH: f64 = 1.0;
+const DEFAULT_TIE_LENGTH: f64 = 28.0;
+const DEFAULT_TIE_WIDTH: f64 = 2.5;
+const DEFAULT_TIE_SPACING: f64 = 5.5;
+
+/// A piece of track following a centerline, which is a bezier for flex track
+/// but can be any track curve. The rails are offset by half of the gauge to
+/// either side of the centerline and the ties are laid across it.
+pub struct FlexTrack<C: TrackCurve = Bezier> {
+    centerline: C,
+    gauge: f64,
+    rail_width: f64,
+    tie_length: f64,
+    tie_width: f64,
+    tie_spacing: f64,
+}
+
+impl<C: TrackCurve> FlexTrack<C> {
+    /// Creates track along the centerline with HO scale dimensions
+    pub fn new(centerline: C) -> Self {
+        FlexTrack {
+            centerline,
+            gauge: DEFAULT_GAUGE,
+            rail_width: DEFAULT_RAIL_WIDTH,
+            tie_length: DEFAULT_TIE_LENGTH,
+            tie_width: DEFAULT_TIE_WIDTH,
+            tie_spacing: DEFAULT_TIE_SPACING,
+        }
+    }
+
+    pub fn centerline(&self) -> &C {
+        &self.centerline
+    }
+
+    pub fn centerline_mut(&mut self) -> &mut C {
+        &mut self.centerline
+    }
+
+    pub fn gauge(&self) -> f64 {
+        self.gauge
+    }
+
+    pub fn set_gauge(&mut self, gauge: f64) {
+        self.gauge = gauge;
+    }
+
+    pub fn rail_width(&self) -> f64 {
+        self.rail_width
+    }
+
+    pub fn set_rail_width(&mut self, rail_width: f64) {
+        self.rail_width = rail_width;
+    }
+
+    pub fn tie_length(&self) -> f64 {
+        self.tie_length
+    }
+
+    pub fn set_tie_length(&mut self, tie_length: f64) {
+        self.tie_length = tie_length;
+    }
+
+    pub fn tie_width(&self) -> f64 {
+        self.tie_width
+    }
+
+    pub fn set_tie_width(&mut self, tie_width: f64) {
+        self.tie_width = tie_width;
+    }
+
+    pub fn tie_spacing(&self) -> f64 {
+        self.tie_spacing
+    }
+
+    pub fn set_tie_spacing(&mut self, tie_spacing: f64) {
+        self.tie_spacing = tie_spacing;
+    }
+
+    /// Gets the distance from the centerline to the outer edge of the track,
+    /// which is the end of the ties or the outside of the rails.
+    pub fn half_width(&self) -> f64 {
+        (self.tie_length * 0.5).max((self.gauge + self.rail_width) * 0.5)
+    }
+
+    /// Gets the axis aligned bounding box of the whole track.
+    ///
+    /// Every point of the track is within half of the track width of the
+    /// centerline, so the bounds of the centerline grown by that distance
+    /// contain the track. The tie corners at the ends of the track extend up
+    /// to half of the tie width beyond the centerline ends and are included
+    /// in the padding.
+    pub fn bounds(&self) -> Rect {
+        let half_width = self.half_width();
+        let padding = (half_width * half_width + self.tie_width * self.tie_width * 0.25).sqrt();
+        self.centerline.track_bounds().inflate(padding)
+    }
+
+    /// Gets the outlines of the ties, which are spaced evenly along the
+    /// centerline starting half of the tie spacing from its start. Each tie
+    /// is a rectangle centered on the centerline and square to it.
+    pub fn ties(&self) -> Vec<Polygon> {
+        let length = self.centerline.track_length();
+        if self.tie_spacing <= 0.0 || length < self.tie_spacing * 0.5 {
+            return Vec::new();
+        }
+        let count = ((length - self.tie_spacing * 0.5) / self.tie_spacing) as usize + 1;
+        (0..count)
+            .map(|i| {
+                let s = (i as f64 + 0.5) * self.tie_spacing;
+                let center = self.centerline.point_along(s);
+                let tangent = self.centerline.tangent_along(s);
+                let along = tangent * (self.tie_width * 0.5);
+                let across = tangent.perpendicular() * (self.tie_length * 0.5);
+                Polygon {
+                    points: vec![
+                        center - along - across,
+                        center + along - across,
+                        center + along + across,
+                        center - along + across,
+                    ],
+                }
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::geometry::{Arc, Line, Point};
+
+    #[test]
+    fn test_bounds() {
+        let track = FlexTrack::new(Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+                Point { x: 200.0, y: 0.0 },
+                Point { x: 300.0, y: 0.0 },
+            ],
+            0.1,
+        ));
+        assert_eq!(track.half_width(), 14.0);
+        let r = track.bounds();
+        assert!(r.x < -14.0 && r.x > -15.0);
+        assert!(r.y < -14.0 && r.y > -15.0);
+        assert!(r.x + r.width > 314.0);
+        assert!(r.contains(Point { x: 0.0, y: 14.0 }));
+        assert!(r.contains(Point { x: 300.0, y: -14.0 }));
+    }
+
+    #[test]
+    fn test_ties() {
+        let track = FlexTrack::new(Line::new(
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 55.0, y: 0.0 },
+        ));
+        let ties = track.ties();
+        assert_eq!(ties.len(), 10);
+        assert_eq!(
+            ties[0].points,
+            vec![
+                Point { x: 1.5, y: -14.0 },
+                Point { x: 4.0, y: -14.0 },
+                Point { x: 4.0, y: 14.0 },
+                Point { x: 1.5, y: 14.0 },
+            ]
+        );
+
+        // ties on a curve are square to it, with the left hand end inside
+        // of a counter-clockwise curve
+        let radius = 457.2;
+        let arc = Arc::new(Point { x: 0.0, y: 0.0 }, radius, 0.0, 1.0);
+        let track = FlexTrack::new(arc);
+        for tie in track.ties() {
+            let outer = (tie.points[0] + tie.points[1]) * 0.5;
+            let inner = (tie.points[2] + tie.points[3]) * 0.5;
+            assert!((inner.magnitude() - (radius - 14.0)).abs() < 1e-2);
+            assert!((outer.magnitude() - (radius + 14.0)).abs() < 1e-2);
+        }
+    }
 }
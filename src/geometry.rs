@@ -1,22 +1,22 @@
-use windows::Win32::Graphics::Direct2D::Common::D2D_POINT_2F;
+use std::ops::{Add, Mul, Neg, Sub};
 
 #[derive(Debug, Clone)]
 pub struct Rect {
-    pub x: f32,
-    pub y: f32,
-    pub width: f32,
-    pub height: f32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Point {
-    pub x: f32,
-    pub y: f32,
+    pub x: f64,
+    pub y: f64,
 }
 
 impl Point {
-    pub fn distance(&self, p: &Point) -> f32 {
-        f32::sqrt((self.x - p.x) * (self.x - p.x) + (self.y - p.y) * (self.y - p.y))
+    pub fn distance(&self, p: &Point) -> f64 {
+        f64::sqrt((self.x - p.x) * (self.x - p.x) + (self.y - p.y) * (self.y - p.y))
     }
 
     /// Gets the slope of a line segment defined by the endpoints self and p
@@ -28,42 +28,100 @@ impl Point {
     ///      m = ---------
     ///          (x2 - x1)
     /// the point passed in the parameter list is considered as x2, y2
-    pub fn slope(&self, p: &Point) -> f32 {
+    pub fn slope(&self, p: &Point) -> f64 {
         let cy = p.y - self.y;
         let cx = p.x - self.x;
         if cx == 0.0 {
-            return f32::NAN;
+            return f64::NAN;
         }
         cy / cx
     }
+
+    /// Gets the dot product of self and p treated as vectors
+    pub fn dot(&self, p: &Point) -> f64 {
+        self.x * p.x + self.y * p.y
+    }
+
+    /// Gets the z component of the cross product of self and p treated as
+    /// vectors. The result is positive when p is counter-clockwise from self.
+    pub fn cross(&self, p: &Point) -> f64 {
+        self.x * p.y - self.y * p.x
+    }
+
+    /// Gets the length of self treated as a vector from the origin
+    pub fn magnitude(&self) -> f64 {
+        f64::sqrt(self.x * self.x + self.y * self.y)
+    }
+
+    /// Gets the unit vector in the direction of self, or None if self is a
+    /// zero length vector.
+    pub fn normalize(&self) -> Option<Point> {
+        let m = self.magnitude();
+        if m <= f64::EPSILON {
+            return None;
+        }
+        Some(Point {
+            x: self.x / m,
+            y: self.y / m,
+        })
+    }
+
+    /// Gets self rotated by 90 degrees counter-clockwise, i.e. the left hand
+    /// normal of self treated as a direction vector.
+    pub fn perpendicular(&self) -> Point {
+        Point {
+            x: -self.y,
+            y: self.x,
+        }
+    }
 }
 
-impl From<D2D_POINT_2F> for Point {
-    fn from(p: D2D_POINT_2F) -> Self {
-        Point { x: p.x, y: p.y }
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, p: Point) -> Point {
+        Point {
+            x: self.x + p.x,
+            y: self.y + p.y,
+        }
     }
 }
 
-impl From<&D2D_POINT_2F> for Point {
-    fn from(p: &D2D_POINT_2F) -> Self {
-        Point { x: p.x, y: p.y }
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, p: Point) -> Point {
+        Point {
+            x: self.x - p.x,
+            y: self.y - p.y,
+        }
     }
 }
 
-impl From<Point> for D2D_POINT_2F {
-    fn from(p: Point) -> Self {
-        D2D_POINT_2F { x: p.x, y: p.y }
+impl Mul<f64> for Point {
+    type Output = Point;
+
+    fn mul(self, s: f64) -> Point {
+        Point {
+            x: self.x * s,
+            y: self.y * s,
+        }
     }
 }
 
-impl From<&Point> for D2D_POINT_2F {
-    fn from(p: &Point) -> Self {
-        D2D_POINT_2F { x: p.x, y: p.y }
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point {
+            x: -self.x,
+            y: -self.y,
+        }
     }
 }
 
 impl Rect {
-    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
         Rect {
             x,
             y,
@@ -73,19 +131,379 @@ impl Rect {
     }
 
     pub fn contains(&self, p: Point) -> bool {
-        p.x >= self.x && p.y <= self.x + self.width && p.y >= self.y && p.y <= self.y + self.height
+        p.x >= self.x && p.x <= self.x + self.width && p.y >= self.y && p.y <= self.y + self.height
+    }
+
+    /// Creates the smallest rectangle containing all of the points, or None
+    /// if there are no points.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Point>) -> Option<Rect> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let (mut min, mut max) = (*first, *first);
+        for p in points {
+            min.x = min.x.min(p.x);
+            min.y = min.y.min(p.y);
+            max.x = max.x.max(p.x);
+            max.y = max.y.max(p.y);
+        }
+        Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+    }
+
+    /// Gets the smallest rectangle containing both self and r
+    pub fn union(&self, r: &Rect) -> Rect {
+        let x = self.x.min(r.x);
+        let y = self.y.min(r.y);
+        let right = (self.x + self.width).max(r.x + r.width);
+        let bottom = (self.y + self.height).max(r.y + r.height);
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    /// Gets self grown by the padding on every side
+    pub fn inflate(&self, padding: f64) -> Rect {
+        Rect::new(
+            self.x - padding,
+            self.y - padding,
+            self.width + padding * 2.0,
+            self.height + padding * 2.0,
+        )
+    }
+
+    /// Checks if self and r overlap. Rectangles that share an edge overlap.
+    pub fn intersects(&self, r: &Rect) -> bool {
+        self.x <= r.x + r.width
+            && r.x <= self.x + self.width
+            && self.y <= r.y + r.height
+            && r.y <= self.y + self.height
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Line {
     pub start: Point,
     pub end: Point,
 }
 
+impl Line {
+    pub fn new(start: Point, end: Point) -> Self {
+        Line { start, end }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.start.distance(&self.end)
+    }
+
+    /// Gets the point on the line at parameter u where u = 0.0 is the start
+    /// and u = 1.0 is the end
+    pub fn point_at(&self, u: f64) -> Point {
+        self.start + (self.end - self.start) * u
+    }
+}
+
 pub struct Polygon {
     pub points: Vec<Point>,
 }
 
+/// A position and direction of travel, such as the end of a piece of track.
+/// The heading is in radians counter-clockwise from the x axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub point: Point,
+    pub heading: f64,
+}
+
+impl Pose {
+    pub fn new(point: Point, heading: f64) -> Self {
+        Pose { point, heading }
+    }
+
+    /// Gets the unit vector in the direction of travel
+    pub fn direction(&self) -> Point {
+        Point {
+            x: self.heading.cos(),
+            y: self.heading.sin(),
+        }
+    }
+}
+
+/// A circular arc around center that starts at start_angle and turns
+/// through sweep radians, counter-clockwise when the sweep is positive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arc {
+    pub center: Point,
+    pub radius: f64,
+    pub start_angle: f64,
+    pub sweep: f64,
+}
+
+impl Arc {
+    pub fn new(center: Point, radius: f64, start_angle: f64, sweep: f64) -> Self {
+        Arc {
+            center,
+            radius,
+            start_angle,
+            sweep,
+        }
+    }
+
+    /// Gets the point on the arc at parameter u where u = 0.0 is the start
+    /// and u = 1.0 is the end
+    pub fn point_at(&self, u: f64) -> Point {
+        let angle = self.start_angle + self.sweep * u;
+        self.center
+            + Point {
+                x: angle.cos(),
+                y: angle.sin(),
+            } * self.radius
+    }
+
+    /// Gets the unit tangent in the direction of travel at parameter u
+    pub fn tangent_at(&self, u: f64) -> Point {
+        let angle = self.start_angle + self.sweep * u;
+        Point {
+            x: -angle.sin(),
+            y: angle.cos(),
+        } * self.sweep.signum()
+    }
+
+    pub fn start(&self) -> Point {
+        self.point_at(0.0)
+    }
+
+    pub fn end(&self) -> Point {
+        self.point_at(1.0)
+    }
+
+    pub fn length(&self) -> f64 {
+        self.radius * self.sweep.abs()
+    }
+
+    /// Checks if the arc passes through the angle in radians from its center
+    pub fn contains_angle(&self, angle: f64) -> bool {
+        // angle from the start, measured in the direction of the sweep
+        let from_start = ((angle - self.start_angle) * self.sweep.signum())
+            .rem_euclid(2.0 * std::f64::consts::PI);
+        from_start <= self.sweep.abs()
+    }
+
+    /// Gets the distance from p to the closest point on the arc
+    pub fn distance_to(&self, p: &Point) -> f64 {
+        let v = *p - self.center;
+        if self.contains_angle(v.y.atan2(v.x)) {
+            (v.magnitude() - self.radius).abs()
+        } else {
+            p.distance(&self.start()).min(p.distance(&self.end()))
+        }
+    }
+}
+
+/// A 2D affine transform
+///
+///     x' = m11 * x + m21 * y + dx
+///     y' = m12 * x + m22 * y + dy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub m11: f64,
+    pub m12: f64,
+    pub m21: f64,
+    pub m22: f64,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl Affine {
+    pub fn identity() -> Self {
+        Affine::scale_xy(1.0, 1.0)
+    }
+
+    pub fn translate(cx: f64, cy: f64) -> Self {
+        Affine {
+            dx: cx,
+            dy: cy,
+            ..Affine::identity()
+        }
+    }
+
+    /// Creates a counter-clockwise rotation by angle radians about the origin
+    pub fn rotate(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Affine {
+            m11: cos,
+            m12: sin,
+            m21: -sin,
+            m22: cos,
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+
+    /// Creates a scale by sx along the x axis and sy along the y axis about
+    /// the origin
+    pub fn scale_xy(sx: f64, sy: f64) -> Self {
+        Affine {
+            m11: sx,
+            m12: 0.0,
+            m21: 0.0,
+            m22: sy,
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+
+    /// Creates the reflection across the infinite line through the axis end
+    /// points. A zero length axis gives the identity.
+    pub fn mirror(axis: &Line) -> Self {
+        let Some(u) = (axis.end - axis.start).normalize() else {
+            return Affine::identity();
+        };
+        // reflection across the direction u is 2 * u * u^T - I
+        let reflect = Affine {
+            m11: 2.0 * u.x * u.x - 1.0,
+            m12: 2.0 * u.x * u.y,
+            m21: 2.0 * u.x * u.y,
+            m22: 2.0 * u.y * u.y - 1.0,
+            dx: 0.0,
+            dy: 0.0,
+        };
+        reflect.about(axis.start)
+    }
+
+    /// Gets this transform applied about the center rather than the origin
+    pub fn about(&self, center: Point) -> Self {
+        Affine::translate(-center.x, -center.y)
+            .then(self)
+            .then(&Affine::translate(center.x, center.y))
+    }
+
+    /// Gets the transform that applies self followed by m
+    pub fn then(&self, m: &Affine) -> Self {
+        Affine {
+            m11: self.m11 * m.m11 + self.m12 * m.m21,
+            m12: self.m11 * m.m12 + self.m12 * m.m22,
+            m21: self.m21 * m.m11 + self.m22 * m.m21,
+            m22: self.m21 * m.m12 + self.m22 * m.m22,
+            dx: self.dx * m.m11 + self.dy * m.m21 + m.dx,
+            dy: self.dx * m.m12 + self.dy * m.m22 + m.dy,
+        }
+    }
+
+    /// Gets the determinant of the linear part. It is negative when the
+    /// transform mirrors, which swaps left and right curves.
+    pub fn determinant(&self) -> f64 {
+        self.m11 * self.m22 - self.m12 * self.m21
+    }
+
+    /// Gets the inverse transform, or None if the transform is singular
+    pub fn inverse(&self) -> Option<Affine> {
+        let det = self.determinant();
+        if det.abs() <= f64::EPSILON {
+            return None;
+        }
+        let m11 = self.m22 / det;
+        let m12 = -self.m12 / det;
+        let m21 = -self.m21 / det;
+        let m22 = self.m11 / det;
+        Some(Affine {
+            m11,
+            m12,
+            m21,
+            m22,
+            dx: -(self.dx * m11 + self.dy * m21),
+            dy: -(self.dx * m12 + self.dy * m22),
+        })
+    }
+
+    /// Gets the point p transformed
+    pub fn apply(&self, p: Point) -> Point {
+        Point {
+            x: self.m11 * p.x + self.m21 * p.y + self.dx,
+            y: self.m12 * p.x + self.m22 * p.y + self.dy,
+        }
+    }
+}
+
+/// Shapes that can be moved, rotated, scaled and mirrored in the layout.
+///
+/// Only `transform` needs to be implemented, the other operations are
+/// built from it. Angles are in radians, counter-clockwise.
+pub trait Transform {
+    /// Applies the affine transform to the shape
+    fn transform(&mut self, m: &Affine);
+
+    fn translate(&mut self, cx: f64, cy: f64) {
+        self.transform(&Affine::translate(cx, cy));
+    }
+
+    /// Rotates the shape by angle radians about the center
+    fn rotate(&mut self, angle: f64, center: Point) {
+        self.transform(&Affine::rotate(angle).about(center));
+    }
+
+    /// Scales the shape uniformly about the center
+    fn scale(&mut self, s: f64, center: Point) {
+        self.scale_xy(s, s, center);
+    }
+
+    /// Scales the shape by sx along the x axis and sy along the y axis
+    /// about the center
+    fn scale_xy(&mut self, sx: f64, sy: f64, center: Point) {
+        self.transform(&Affine::scale_xy(sx, sy).about(center));
+    }
+
+    /// Reflects the shape across the infinite line through the axis
+    fn mirror(&mut self, axis: &Line) {
+        self.transform(&Affine::mirror(axis));
+    }
+}
+
+impl Transform for Point {
+    fn transform(&mut self, m: &Affine) {
+        *self = m.apply(*self);
+    }
+}
+
+impl Transform for Line {
+    fn transform(&mut self, m: &Affine) {
+        self.start.transform(m);
+        self.end.transform(m);
+    }
+}
+
+impl Transform for Polygon {
+    fn transform(&mut self, m: &Affine) {
+        for p in self.points.iter_mut() {
+            p.transform(m);
+        }
+    }
+}
+
+impl Transform for Rect {
+    /// Transforms the rectangle to the axis aligned bounds of its
+    /// transformed corners, since a rotated rectangle is no longer axis
+    /// aligned.
+    fn transform(&mut self, m: &Affine) {
+        let corners = [
+            Point {
+                x: self.x,
+                y: self.y,
+            },
+            Point {
+                x: self.x + self.width,
+                y: self.y,
+            },
+            Point {
+                x: self.x,
+                y: self.y + self.height,
+            },
+            Point {
+                x: self.x + self.width,
+                y: self.y + self.height,
+            },
+        ]
+        .map(|p| m.apply(p));
+        *self = Rect::from_points(corners.iter()).unwrap();
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -95,6 +513,27 @@ mod test {
         let r = Rect::new(10.0, 10.0, 10.0, 10.0);
         assert!(r.contains(Point { x: 15.0, y: 15.0 }));
         assert!(!r.contains(Point { x: 5.0, y: 5.0 }));
+        assert!(!r.contains(Point { x: 25.0, y: 15.0 }));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let points = [
+            Point { x: 1.0, y: 5.0 },
+            Point { x: -2.0, y: 3.0 },
+            Point { x: 4.0, y: 8.0 },
+        ];
+        let r = Rect::from_points(points.iter()).unwrap();
+        assert_eq!((r.x, r.y, r.width, r.height), (-2.0, 3.0, 6.0, 5.0));
+        assert!(Rect::from_points([].iter()).is_none());
+
+        let u = r.union(&Rect::new(10.0, 0.0, 1.0, 1.0));
+        assert_eq!((u.x, u.y, u.width, u.height), (-2.0, 0.0, 13.0, 8.0));
+        let i = r.inflate(1.0);
+        assert_eq!((i.x, i.y, i.width, i.height), (-3.0, 2.0, 8.0, 7.0));
+
+        assert!(r.intersects(&Rect::new(4.0, 8.0, 1.0, 1.0)));
+        assert!(!r.intersects(&Rect::new(5.0, 0.0, 1.0, 1.0)));
     }
 
     #[test]
@@ -126,4 +565,103 @@ mod test {
         let p2 = Point { x: -10.0, y: -10.0 };
         assert_eq!(p1.distance(&p2).floor(), 14.0);
     }
+
+    #[test]
+    fn test_vector_ops() {
+        let p1 = Point { x: 1.0, y: 2.0 };
+        let p2 = Point { x: 3.0, y: -1.0 };
+        assert_eq!(p1 + p2, Point { x: 4.0, y: 1.0 });
+        assert_eq!(p1 - p2, Point { x: -2.0, y: 3.0 });
+        assert_eq!(p1 * 2.0, Point { x: 2.0, y: 4.0 });
+        assert_eq!(p1.dot(&p2), 1.0);
+        assert_eq!(p1.cross(&p2), -7.0);
+        assert_eq!(Point { x: 3.0, y: 4.0 }.magnitude(), 5.0);
+    }
+
+    fn assert_near(a: Point, b: Point) {
+        assert!(a.distance(&b) < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_affine() {
+        let p = Point { x: 2.0, y: 1.0 };
+        assert_eq!(Affine::identity().apply(p), p);
+        assert_near(
+            Affine::rotate(std::f64::consts::FRAC_PI_2).apply(p),
+            Point { x: -1.0, y: 2.0 },
+        );
+        assert_eq!(
+            Affine::scale_xy(2.0, 3.0).apply(p),
+            Point { x: 4.0, y: 3.0 }
+        );
+
+        let m = Affine::rotate(0.3)
+            .then(&Affine::scale_xy(2.0, 0.5))
+            .then(&Affine::translate(5.0, -1.0));
+        assert_near(m.inverse().unwrap().apply(m.apply(p)), p);
+        assert!(Affine::scale_xy(1.0, 0.0).inverse().is_none());
+
+        let axis = Line::new(Point { x: 0.0, y: 1.0 }, Point { x: 1.0, y: 2.0 });
+        let mirror = Affine::mirror(&axis);
+        assert!(mirror.determinant() < 0.0);
+        assert_near(
+            mirror.apply(Point { x: 0.0, y: 1.0 }),
+            Point { x: 0.0, y: 1.0 },
+        );
+        assert_near(
+            mirror.apply(Point { x: 1.0, y: 0.0 }),
+            Point { x: -1.0, y: 2.0 },
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut p = Point { x: 1.0, y: 0.0 };
+        p.translate(1.0, 0.0);
+        assert_eq!(p, Point { x: 2.0, y: 0.0 });
+        p.rotate(std::f64::consts::PI, Point { x: 1.0, y: 0.0 });
+        assert_near(p, Point { x: 0.0, y: 0.0 });
+
+        let mut line = Line::new(Point { x: 1.0, y: 1.0 }, Point { x: 3.0, y: 1.0 });
+        line.scale(2.0, Point { x: 1.0, y: 1.0 });
+        assert_eq!(line.length(), 4.0);
+
+        let mut polygon = Polygon {
+            points: vec![Point { x: 1.0, y: 0.0 }, Point { x: 2.0, y: 3.0 }],
+        };
+        polygon.mirror(&Line::new(
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 0.0, y: 1.0 },
+        ));
+        assert_eq!(polygon.points[1], Point { x: -2.0, y: 3.0 });
+
+        let mut r = Rect::new(0.0, 0.0, 2.0, 1.0);
+        r.rotate(std::f64::consts::FRAC_PI_2, Point { x: 0.0, y: 0.0 });
+        assert!((r.x + 1.0).abs() < 1e-5 && r.y.abs() < 1e-5);
+        assert!((r.width - 1.0).abs() < 1e-5 && (r.height - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_closure() {
+        // a thousand circles of 22.5 degree sectional track at the far
+        // corner of a 30 foot layout close to well under a micron
+        let center = Point {
+            x: 9144.0,
+            y: 9144.0,
+        };
+        let start = center + Point { x: 457.2, y: 0.0 };
+        let mut p = start;
+        for _ in 0..16000 {
+            p.rotate(std::f64::consts::PI / 8.0, center);
+        }
+        assert!(p.distance(&start) < 1e-6);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let n = Point { x: 3.0, y: 4.0 }.normalize().unwrap();
+        assert_eq!(n, Point { x: 0.6, y: 0.8 });
+        assert!(Point { x: 0.0, y: 0.0 }.normalize().is_none());
+        assert_eq!(n.perpendicular(), Point { x: -0.8, y: 0.6 });
+    }
 }
@@ -0,0 +1,585 @@
+use crate::{
+    bezier::{binomial, segment_distance, split_ctrl_points, Bezier},
+    curve::Curve,
+    geometry::{Line, Point, Rect},
+};
+
+/// Limits the subdivision depth when intersecting two curves
+const INTERSECT_MAX_DEPTH: u32 = 24;
+/// Newton iterations used to refine an intersection of two curves
+const REFINE_ITERATIONS: usize = 16;
+/// Bisection iterations used to locate a root of a monotonic interval
+const ROOT_ITERATIONS: usize = 32;
+/// Intersections where the sine of the angle between the tangents is below
+/// this value are reported as touches rather than crossings.
+const TANGENT_EPSILON: f64 = 1e-3;
+/// Samples used to check that two curves coincide between two points
+const OVERLAP_SAMPLES: usize = 8;
+
+/// A point shared by two curves, at parameter t on the first curve and
+/// parameter u on the second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intersection {
+    pub t: f64,
+    pub u: f64,
+    pub point: Point,
+}
+
+/// A stretch shared by two curves, from t.0 to t.1 on the first curve and
+/// from u.0 to u.1 on the second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overlap {
+    pub t: (f64, f64),
+    pub u: (f64, f64),
+}
+
+/// The intersections of two curves sorted by the parameter on the first
+/// curve. Points where the curves cross, points where they touch with a
+/// common tangent and stretches where they coincide are kept separately.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Intersections {
+    pub crossings: Vec<Intersection>,
+    pub touches: Vec<Intersection>,
+    pub overlaps: Vec<Overlap>,
+}
+
+impl Intersections {
+    pub fn is_empty(&self) -> bool {
+        self.crossings.is_empty() && self.touches.is_empty() && self.overlaps.is_empty()
+    }
+}
+
+impl Bezier {
+    /// Gets the intersections of the curve with the line segment. Points
+    /// within tolerance of the line are treated as on the line.
+    ///
+    /// The signed distance of the curve from the line is itself a polynomial
+    /// in t. Its turning points split [0, 1] into intervals on which the
+    /// distance is monotonic, so each interval has at most one crossing and
+    /// a turning point within tolerance of the line is a touch.
+    pub fn intersect_line(&self, line: &Line, tolerance: f64) -> Intersections {
+        let mut result = Intersections::default();
+        let direction = line.end - line.start;
+        let len2 = direction.dot(&direction);
+        let Some(normal) = direction.normalize().map(|d| d.perpendicular()) else {
+            return result;
+        };
+        let p = self.control_points();
+        let along = |t: f64| (self.point_at(t) - line.start).dot(&direction) / len2;
+        let distance = power_basis(
+            &p.iter()
+                .map(|c| (*c - line.start).dot(&normal))
+                .collect::<Vec<f64>>(),
+        );
+
+        if p.iter()
+            .all(|c| (*c - line.start).dot(&normal).abs() <= tolerance)
+        {
+            // the curve lies on the line, so find where it is within the
+            // segment from the roots of along(t) = 0 and along(t) = 1
+            let along_coefficients = power_basis(
+                &p.iter()
+                    .map(|c| (*c - line.start).dot(&direction) / len2)
+                    .collect::<Vec<f64>>(),
+            );
+            let mut bounds = vec![0.0, 1.0];
+            for offset in [0.0, 1.0] {
+                let mut shifted = along_coefficients.clone();
+                shifted[0] -= offset;
+                bounds.extend(polynomial_roots(&shifted, 0.0).0);
+            }
+            bounds.sort_by(f64::total_cmp);
+            for w in bounds.windows(2) {
+                if w[1] - w[0] <= f64::EPSILON {
+                    continue;
+                }
+                let mid = along((w[0] + w[1]) * 0.5);
+                if (0.0..=1.0).contains(&mid) {
+                    result.overlaps.push(Overlap {
+                        t: (w[0], w[1]),
+                        u: (along(w[0]).clamp(0.0, 1.0), along(w[1]).clamp(0.0, 1.0)),
+                    });
+                }
+            }
+            return result;
+        }
+
+        let (crossings, touches) = polynomial_roots(&distance, tolerance);
+        let on_segment = |t: f64| {
+            let u = along(t);
+            if (-f64::EPSILON..=1.0 + f64::EPSILON).contains(&u) {
+                Some(Intersection {
+                    t,
+                    u: u.clamp(0.0, 1.0),
+                    point: self.point_at(t),
+                })
+            } else {
+                None
+            }
+        };
+        result.crossings = crossings.into_iter().filter_map(on_segment).collect();
+        result.touches = touches.into_iter().filter_map(on_segment).collect();
+        result
+    }
+
+    /// Gets the intersections of the curve with another curve. Points within
+    /// tolerance of each other are treated as coincident.
+    ///
+    /// Both curves are recursively split while the bounds of their control
+    /// points overlap. Once both parts are flat their chords are intersected
+    /// and the result refined with Newton's method on the original curves.
+    /// Two distinct polynomial curves can only coincide over a stretch that
+    /// ends at an end point of one of them, so overlaps are found by projecting the end
+    /// points of each curve onto the other.
+    pub fn intersect(&self, other: &Bezier, tolerance: f64) -> Intersections {
+        let mut result = Intersections {
+            overlaps: self.overlaps(other, tolerance),
+            ..Default::default()
+        };
+        let mut candidates = Vec::new();
+        intersect_parts(
+            self.control_points(),
+            (0.0, 1.0),
+            other.control_points(),
+            (0.0, 1.0),
+            tolerance,
+            0,
+            &mut candidates,
+        );
+        let mut found: Vec<Intersection> = candidates
+            .into_iter()
+            .map(|(t, u)| {
+                let (t, u) = refine_intersection(self, other, t, u);
+                Intersection {
+                    t,
+                    u,
+                    point: self.point_at(t),
+                }
+            })
+            .filter(|i| i.point.distance(&other.point_at(i.u)) <= tolerance)
+            .filter(|i| {
+                !result
+                    .overlaps
+                    .iter()
+                    .any(|o| i.t >= o.t.0 - f64::EPSILON && i.t <= o.t.1 + f64::EPSILON)
+            })
+            .collect();
+        found.sort_by(|a, b| a.t.total_cmp(&b.t));
+
+        // adjacent parts report the same intersection more than once, and a
+        // touch is reported all along the stretch where the curves are within
+        // tolerance, so merge candidates joined by such a stretch
+        let mut merged: Vec<Intersection> = Vec::new();
+        for i in found {
+            if let Some(last) = merged.last_mut() {
+                let mid = self.point_at((last.t + i.t) * 0.5);
+                if last.point.distance(&i.point) <= tolerance
+                    || other.distance_to(&mid) <= tolerance
+                {
+                    if i.point.distance(&other.point_at(i.u))
+                        < last.point.distance(&other.point_at(last.u))
+                    {
+                        *last = i;
+                    }
+                    continue;
+                }
+            }
+            merged.push(i);
+        }
+        for i in merged {
+            let angle = self.tangent_at(i.t).cross(&other.tangent_at(i.u)).abs();
+            if angle <= TANGENT_EPSILON {
+                result.touches.push(i);
+            } else {
+                result.crossings.push(i);
+            }
+        }
+        result
+    }
+
+    /// Gets the stretches where the curve coincides with the other curve
+    fn overlaps(&self, other: &Bezier, tolerance: f64) -> Vec<Overlap> {
+        let mut ends = Vec::new();
+        for t in [0.0, 1.0] {
+            let projection = other.project(&self.point_at(t));
+            if projection.distance <= tolerance {
+                ends.push((t, projection.t));
+            }
+        }
+        for u in [0.0, 1.0] {
+            let projection = self.project(&other.point_at(u));
+            if projection.distance <= tolerance {
+                ends.push((projection.t, u));
+            }
+        }
+        ends.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ends.dedup_by(|a, b| {
+            (a.0 - b.0).abs() <= f64::EPSILON && (a.1 - b.1).abs() <= f64::EPSILON
+        });
+
+        let mut overlaps: Vec<Overlap> = Vec::new();
+        for (i, start) in ends.iter().enumerate() {
+            for end in ends.iter().skip(i + 1) {
+                if end.0 - start.0 <= f64::EPSILON || (end.1 - start.1).abs() <= f64::EPSILON {
+                    continue;
+                }
+                let coincident = (1..OVERLAP_SAMPLES).all(|k| {
+                    let s = k as f64 / OVERLAP_SAMPLES as f64;
+                    let p = self.point_at(start.0 + (end.0 - start.0) * s);
+                    let q = other.point_at(start.1 + (end.1 - start.1) * s);
+                    other.distance_to(&p) <= tolerance && self.distance_to(&q) <= tolerance
+                });
+                let contained = overlaps.iter().any(|o| o.t.0 <= start.0 && o.t.1 >= end.0);
+                if coincident && !contained {
+                    overlaps.retain(|o| !(o.t.0 >= start.0 && o.t.1 <= end.0));
+                    overlaps.push(Overlap {
+                        t: (start.0, end.0),
+                        u: (start.1, end.1),
+                    });
+                }
+            }
+        }
+        overlaps
+    }
+}
+
+/// Collects the approximate parameters where the parts with control points a
+/// and b intersect. The parts span the ranges ta and ub of the curves.
+fn intersect_parts(
+    a: &[Point],
+    ta: (f64, f64),
+    b: &[Point],
+    ub: (f64, f64),
+    tolerance: f64,
+    depth: u32,
+    candidates: &mut Vec<(f64, f64)>,
+) {
+    let bounds_a = Rect::from_points(a.iter()).unwrap().inflate(tolerance);
+    let bounds_b = Rect::from_points(b.iter()).unwrap();
+    if !bounds_a.intersects(&bounds_b) {
+        return;
+    }
+    let flat = |p: &[Point]| {
+        let (start, end) = (&p[0], &p[p.len() - 1]);
+        p[1..p.len() - 1]
+            .iter()
+            .all(|q| segment_distance(q, start, end) <= tolerance * 0.5)
+    };
+    if depth >= INTERSECT_MAX_DEPTH || (flat(a) && flat(b)) {
+        if let Some((s, v)) =
+            closest_on_segments(&a[0], &a[a.len() - 1], &b[0], &b[b.len() - 1], tolerance)
+        {
+            candidates.push((ta.0 + (ta.1 - ta.0) * s, ub.0 + (ub.1 - ub.0) * v));
+        }
+        return;
+    }
+    let (a0, a1) = split_ctrl_points(a, 0.5);
+    let (b0, b1) = split_ctrl_points(b, 0.5);
+    let tm = (ta.0 + ta.1) * 0.5;
+    let um = (ub.0 + ub.1) * 0.5;
+    for (pa, ra) in [(&a0, (ta.0, tm)), (&a1, (tm, ta.1))] {
+        for (pb, rb) in [(&b0, (ub.0, um)), (&b1, (um, ub.1))] {
+            intersect_parts(pa, ra, pb, rb, tolerance, depth + 1, candidates);
+        }
+    }
+}
+
+/// Gets the parameters of the closest points of the segments a0-a1 and b0-b1
+/// if they are within tolerance of each other.
+fn closest_on_segments(
+    a0: &Point,
+    a1: &Point,
+    b0: &Point,
+    b1: &Point,
+    tolerance: f64,
+) -> Option<(f64, f64)> {
+    let da = *a1 - *a0;
+    let db = *b1 - *b0;
+    let denominator = da.cross(&db);
+    if denominator.abs() > f64::EPSILON {
+        let s = (*b0 - *a0).cross(&db) / denominator;
+        let v = (*b0 - *a0).cross(&da) / denominator;
+        if (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&v) {
+            return Some((s, v));
+        }
+    }
+    // the segments do not cross, so the closest points include an end point
+    let project = |p: &Point, start: &Point, d: &Point| {
+        let len2 = d.dot(d);
+        if len2 <= f64::EPSILON {
+            0.0
+        } else {
+            ((*p - *start).dot(d) / len2).clamp(0.0, 1.0)
+        }
+    };
+    let options = [
+        (0.0, project(a0, b0, &db)),
+        (1.0, project(a1, b0, &db)),
+        (project(b0, a0, &da), 0.0),
+        (project(b1, a0, &da), 1.0),
+    ];
+    options
+        .into_iter()
+        .map(|(s, v)| (s, v, (*a0 + da * s).distance(&(*b0 + db * v))))
+        .filter(|(_, _, d)| *d <= tolerance)
+        .min_by(|x, y| x.2.total_cmp(&y.2))
+        .map(|(s, v, _)| (s, v))
+}
+
+/// Refines the intersection of the curves a(t) = b(u) with Newton's method.
+/// The estimate is kept when the curves are tangent and the step is
+/// undefined.
+fn refine_intersection(a: &Bezier, b: &Bezier, t: f64, u: f64) -> (f64, f64) {
+    let (mut t, mut u) = (t, u);
+    for _ in 0..REFINE_ITERATIONS {
+        let f = a.point_at(t) - b.point_at(u);
+        let da = a.derivative_at(t);
+        let db = b.derivative_at(u);
+        // solve da * dt - db * du = -f
+        let det = db.cross(&da);
+        if det.abs() <= f64::EPSILON {
+            break;
+        }
+        let dt = db.cross(&f) / det;
+        let du = da.cross(&f) / det;
+        let (nt, nu) = ((t + dt).clamp(0.0, 1.0), (u + du).clamp(0.0, 1.0));
+        if (a.point_at(nt) - b.point_at(nu)).magnitude() > f.magnitude() {
+            break;
+        }
+        (t, u) = (nt, nu);
+    }
+    (t, u)
+}
+
+/// Converts the Bernstein coefficients of a polynomial to the power basis
+/// coefficients c where the polynomial is the sum of c(k) * t^k.
+///
+///      c(k) = C(n, k) * sum (-1)^(k - i) * C(k, i) * b(i) for i = 0..k
+fn power_basis(b: &[f64]) -> Vec<f64> {
+    let n = b.len() - 1;
+    (0..=n)
+        .map(|k| {
+            let sum: f64 = (0..=k)
+                .map(|i| {
+                    let sign = if (k - i).is_multiple_of(2) { 1.0 } else { -1.0 };
+                    sign * binomial(k, i) * b[i]
+                })
+                .sum();
+            binomial(n, k) * sum
+        })
+        .collect()
+}
+
+fn polynomial(c: &[f64], t: f64) -> f64 {
+    c.iter().rev().fold(0.0, |sum, v| sum * t + v)
+}
+
+/// Gets the roots in [0, 1] of the polynomial with the power basis
+/// coefficients c, split into crossings and touches. A touch is a turning
+/// point of the polynomial within tolerance of zero, which also absorbs any
+/// crossings of the neighbouring intervals since the polynomial stays within
+/// tolerance of zero between them.
+///
+/// The turning points are the crossings of the derivative, found
+/// recursively.
+pub(crate) fn polynomial_roots(c: &[f64], tolerance: f64) -> (Vec<f64>, Vec<f64>) {
+    let mut bounds = vec![0.0];
+    let mut turning: Vec<f64> = if c.len() > 2 {
+        let derivative: Vec<f64> = c
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, v)| k as f64 * v)
+            .collect();
+        polynomial_roots(&derivative, 0.0).0
+    } else {
+        vec![]
+    };
+    turning.retain(|t| *t > 0.0 && *t < 1.0);
+    turning.sort_by(f64::total_cmp);
+    bounds.extend(turning);
+    bounds.push(1.0);
+
+    let interior = bounds.len() - 1;
+    let touch: Vec<bool> = bounds
+        .iter()
+        .enumerate()
+        .map(|(i, t)| i > 0 && i < interior && polynomial(c, *t).abs() <= tolerance)
+        .collect();
+    let touches = bounds
+        .iter()
+        .zip(touch.iter())
+        .filter(|(_, touch)| **touch)
+        .map(|(t, _)| *t)
+        .collect();
+
+    let mut crossings: Vec<f64> = Vec::new();
+    for i in 0..interior {
+        if touch[i] || touch[i + 1] {
+            continue;
+        }
+        let (mut lo, mut hi) = (bounds[i], bounds[i + 1]);
+        let (flo, fhi) = (polynomial(c, lo), polynomial(c, hi));
+        let root = if flo == 0.0 {
+            lo
+        } else if fhi == 0.0 {
+            hi
+        } else if (flo < 0.0) != (fhi < 0.0) {
+            for _ in 0..ROOT_ITERATIONS {
+                let mid = (lo + hi) * 0.5;
+                if (polynomial(c, mid) < 0.0) == (flo < 0.0) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            (lo + hi) * 0.5
+        } else {
+            continue;
+        };
+        if crossings
+            .last()
+            .is_none_or(|last| root - last > f64::EPSILON)
+        {
+            crossings.push(root);
+        }
+    }
+    (crossings, touches)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{fixtures::arch, geometry::Transform};
+
+    const TEST_EPSILON: f64 = 0.01;
+
+    #[test]
+    fn test_intersect_line() {
+        let b = arch(0.25);
+        let line = Line::new(Point { x: -10.0, y: 50.0 }, Point { x: 110.0, y: 50.0 });
+        let result = b.intersect_line(&line, 0.001);
+        assert_eq!(result.crossings.len(), 2);
+        assert!(result.touches.is_empty());
+        for i in result.crossings.iter() {
+            assert!((i.point.y - 50.0).abs() < TEST_EPSILON);
+            assert!(i.point.distance(&line.point_at(i.u)) < TEST_EPSILON);
+        }
+
+        // the segment is too short to reach the second crossing
+        let short = Line::new(Point { x: -10.0, y: 50.0 }, Point { x: 50.0, y: 50.0 });
+        assert_eq!(b.intersect_line(&short, 0.001).crossings.len(), 1);
+
+        // tangent to the top of the arch
+        let top = Line::new(Point { x: 0.0, y: 75.0 }, Point { x: 100.0, y: 75.0 });
+        let result = b.intersect_line(&top, 0.001);
+        assert!(result.crossings.is_empty());
+        assert_eq!(result.touches.len(), 1);
+        assert!((result.touches[0].t - 0.5).abs() < TEST_EPSILON);
+    }
+
+    #[test]
+    fn test_intersect_line_overlap() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 10.0, y: 0.0 },
+                Point { x: 20.0, y: 0.0 },
+                Point { x: 30.0, y: 0.0 },
+            ],
+            0.25,
+        );
+        let line = Line::new(Point { x: 15.0, y: 0.0 }, Point { x: 45.0, y: 0.0 });
+        let result = b.intersect_line(&line, 0.001);
+        assert_eq!(result.overlaps.len(), 1);
+        let overlap = result.overlaps[0];
+        assert!((overlap.t.0 - 0.5).abs() < TEST_EPSILON);
+        assert_eq!(overlap.t.1, 1.0);
+        assert_eq!(overlap.u.0, 0.0);
+        assert!((overlap.u.1 - 0.5).abs() < TEST_EPSILON);
+    }
+
+    #[test]
+    fn test_intersect_curves() {
+        let a = arch(0.25);
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 80.0 },
+                Point { x: 0.0, y: -20.0 },
+                Point { x: 100.0, y: -20.0 },
+                Point { x: 100.0, y: 80.0 },
+            ],
+            0.25,
+        );
+        let result = a.intersect(&b, 0.001);
+        assert_eq!(result.crossings.len(), 2);
+        assert!(result.touches.is_empty());
+        assert!(result.overlaps.is_empty());
+        for i in result.crossings.iter() {
+            assert!(a.point_at(i.t).distance(&b.point_at(i.u)) < TEST_EPSILON);
+        }
+        assert!(result.crossings[0].t < result.crossings[1].t);
+    }
+
+    #[test]
+    fn test_intersect_touch() {
+        // an upside down arch touching the top of the arch
+        let a = arch(0.25);
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 150.0 },
+                Point { x: 0.0, y: 50.0 },
+                Point { x: 100.0, y: 50.0 },
+                Point { x: 100.0, y: 150.0 },
+            ],
+            0.25,
+        );
+        let result = a.intersect(&b, 0.01);
+        assert!(result.crossings.is_empty());
+        assert_eq!(result.touches.len(), 1);
+        assert!((result.touches[0].t - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_intersect_overlap() {
+        let b = arch(0.25);
+        let first = b.subcurve(0.0, 0.6);
+        let second = b.subcurve(0.4, 1.0);
+        let result = first.intersect(&second, 0.01);
+        assert_eq!(result.overlaps.len(), 1);
+        assert!(result.crossings.is_empty());
+        let overlap = result.overlaps[0];
+        assert!(first.point_at(overlap.t.0).distance(&b.point_at(0.4)) < TEST_EPSILON);
+        assert_eq!(overlap.t.1, 1.0);
+        assert_eq!(overlap.u.0, 0.0);
+        assert!(second.point_at(overlap.u.1).distance(&b.point_at(0.6)) < TEST_EPSILON);
+
+        let disjoint = arch(0.25);
+        let mut moved = disjoint.clone();
+        moved.translate(0.0, 200.0);
+        assert!(disjoint.intersect(&moved, 0.01).is_empty());
+    }
+
+    #[test]
+    fn test_intersect_mixed_degree() {
+        let a = arch(0.25);
+        let straight = Bezier::new_with_ctrl_point(
+            [Point { x: -10.0, y: 50.0 }, Point { x: 110.0, y: 50.0 }],
+            0.25,
+        );
+        let result = a.intersect(&straight, 0.001);
+        assert_eq!(result.crossings.len(), 2);
+
+        let quadratic = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 100.0 },
+                Point { x: 50.0, y: -100.0 },
+                Point { x: 100.0, y: 100.0 },
+            ],
+            0.25,
+        );
+        let line = Line::new(Point { x: 0.0, y: 50.0 }, Point { x: 100.0, y: 50.0 });
+        assert_eq!(quadratic.intersect_line(&line, 0.001).crossings.len(), 2);
+        assert_eq!(a.intersect(&quadratic, 0.001).crossings.len(), 2);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::{
     bezier::Bezier,
     direct2d::{color_rgb, create_brush_rgb, create_style},
-    geometry::Point,
+    geometry::{Point, Rect},
+    path::BezierPath,
+    track::TrackCurve,
 };
 use std::sync::Once;
 use windows::{
@@ -34,6 +36,9 @@ static REGISTER_WINDOW_CLASS: Once = Once::new();
 
 const DEFAULT_LAYOUT_COLOR: u32 = 0x5acd7d;
 const DEFAULT_BRUSH_COLOR: u32 = 0x000000;
+const HANDLE_RADIUS: f32 = 10.0;
+/// Maximum distance in layout units of the drawn polylines from the curves
+const DRAW_TOLERANCE: f64 = 0.25;
 pub(crate) struct LayoutView<'a> {
     handle: HWND,
     factory: &'a ID2D1Factory1,
@@ -46,7 +51,7 @@ pub(crate) struct LayoutView<'a> {
 
     dpix: f32,
     dpiy: f32,
-    bezier: Bezier,
+    path: BezierPath,
 }
 
 impl<'a> LayoutView<'a> {
@@ -96,15 +101,7 @@ impl<'a> LayoutView<'a> {
             handle_style: None,
             dpix,
             dpiy,
-            bezier: Bezier::new_with_ctrl_point(
-                [
-                    Point { x: 10.0, y: 10.0 },
-                    Point { x: 100.0, y: 10.0 },
-                    Point { x: 10.0, y: 150.0 },
-                    Point { x: 150.0, y: 150.0 },
-                ],
-                0.05,
-            ),
+            path: LayoutView::default_path(),
         });
 
         // get the parent size
@@ -132,6 +129,30 @@ impl<'a> LayoutView<'a> {
         Ok(view)
     }
 
+    /// Creates the initial layout of two pieces of flex track joined with
+    /// G1 continuity
+    fn default_path() -> BezierPath {
+        let mut path = BezierPath::new(Bezier::new_with_ctrl_point(
+            [
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 100.0, y: 10.0 },
+                Point { x: 10.0, y: 150.0 },
+                Point { x: 150.0, y: 150.0 },
+            ],
+            0.25,
+        ));
+        path.push(Bezier::new_with_ctrl_point(
+            [
+                Point { x: 150.0, y: 150.0 },
+                Point { x: 290.0, y: 150.0 },
+                Point { x: 300.0, y: 250.0 },
+                Point { x: 400.0, y: 250.0 },
+            ],
+            0.25,
+        ));
+        path
+    }
+
     fn release_device(&mut self) {
         self.target = None;
         self.release_device_resources();
@@ -179,34 +200,53 @@ impl<'a> LayoutView<'a> {
             target.BeginDraw();
             target.Clear(Some(&color_rgb(DEFAULT_LAYOUT_COLOR)));
 
-            let curve = self.bezier.curve();
-            let mut prev = &curve[0];
-            for p in curve.iter().skip(1) {
+            // skip curves and handles that are entirely outside of the view
+            let size = target.GetSize();
+            let viewport = Rect::new(0.0, 0.0, size.width as f64, size.height as f64);
+            for segment in self.path.segments() {
+                self.draw_curve(target, segment, &viewport);
+                // draw the control points, which can be in view when the
+                // curve is not
+                for p in segment.control_points().iter() {
+                    let handle = Rect::new(p.x, p.y, 0.0, 0.0).inflate(HANDLE_RADIUS as f64);
+                    if !handle.intersects(&viewport) {
+                        continue;
+                    }
+                    target.DrawEllipse(
+                        &D2D1_ELLIPSE {
+                            point: p.into(),
+                            radiusX: HANDLE_RADIUS,
+                            radiusY: HANDLE_RADIUS,
+                        },
+                        self.handle_brush.as_ref().unwrap(),
+                        1.0,
+                        self.handle_style.as_ref().unwrap(),
+                    );
+                }
+            }
+            target.EndDraw(None, None)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the curve as a polyline unless it is entirely outside of the
+    /// viewport
+    fn draw_curve(&self, target: &ID2D1HwndRenderTarget, curve: &dyn TrackCurve, viewport: &Rect) {
+        if !curve.track_bounds().intersects(viewport) {
+            return;
+        }
+        let points = curve.polyline_within(DRAW_TOLERANCE);
+        for pair in points.windows(2) {
+            unsafe {
                 target.DrawLine(
-                    prev.into(),
-                    p.into(),
+                    (&pair[0]).into(),
+                    (&pair[1]).into(),
                     self.default_brush.as_ref().unwrap(),
                     1.0,
                     None,
                 );
-                prev = p;
-            }
-            // draw the control points
-            for p in self.bezier.control_points().iter() {
-                target.DrawEllipse(
-                    &D2D1_ELLIPSE {
-                        point: p.into(),
-                        radiusX: 10.0,
-                        radiusY: 10.0,
-                    },
-                    self.handle_brush.as_ref().unwrap(),
-                    1.0,
-                    self.handle_style.as_ref().unwrap(),
-                );
             }
-            target.EndDraw(None, None)?;
         }
-        Ok(())
     }
 
     fn create_resources(&mut self) -> Result<()> {
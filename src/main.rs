@@ -24,10 +24,29 @@ use windows::{
 };
 
 mod bezier;
+mod biarc;
+mod clothoid;
+mod constraint;
+mod curvature;
+mod curve;
 mod direct2d;
+mod elastica;
+mod fit;
+mod fixedlength;
+#[cfg(test)]
+mod fixtures;
 mod flextrack;
 mod geometry;
+mod intersect;
 mod layoutview;
+mod morph;
+mod offset;
+mod path;
+mod projection;
+mod rational;
+mod spline;
+mod track;
+mod validity;
 
 static REGISTER_WINDOW_CLASS: Once = Once::new();
 
@@ -0,0 +1,175 @@
+use crate::{
+    curve::{ArcLengthTable, Curve},
+    geometry::Point,
+    path::BezierPath,
+};
+
+/// Fewest intervals at equal distances along each shape that are blended
+/// between the shapes
+const MORPH_MIN_INTERVALS: usize = 8;
+/// Limits the number of intervals blended between the shapes. Shapes that
+/// need more to meet the tolerance are blended less accurately.
+const MORPH_MAX_INTERVALS: usize = 8192;
+
+/// A blend between two track shapes, such as alternative routings of a
+/// mainline, that gives the intermediate shapes for comparing or animating
+/// them.
+///
+/// Both shapes are sampled at the same fractions of their arc length, so
+/// the point a quarter of the way along one shape is blended with the point
+/// a quarter of the way along the other. Matching by arc length rather than
+/// by curve parameter keeps the intermediate shapes evenly spread even when
+/// the shapes have different numbers of segments or control points.
+#[derive(Debug, Clone)]
+pub struct Morph {
+    from: Vec<Point>,
+    to: Vec<Point>,
+    tolerance: f64,
+}
+
+impl Morph {
+    /// Creates the blend from one shape to the other. The tolerance is the
+    /// accuracy of the arc length sampling and the maximum distance of the
+    /// intermediate shapes from the blended points.
+    ///
+    /// The shapes are sampled closely enough that the polyline through the
+    /// samples stays within the tolerance of each shape, which takes more
+    /// samples for longer and sharper shapes. The samples are limited to
+    /// 8192 intervals, so very long shapes, and shapes with a cusp where
+    /// the curvature is infinite, are only followed as closely as that
+    /// many samples allow rather than to the tolerance.
+    pub fn new<A, B>(from: &A, to: &B, tolerance: f64) -> Self
+    where
+        A: Curve + ?Sized,
+        B: Curve + ?Sized,
+    {
+        let from_table = from.arc_length_table(tolerance);
+        let to_table = to.arc_length_table(tolerance);
+        let count =
+            intervals(from, &from_table, tolerance).max(intervals(to, &to_table, tolerance));
+        Morph {
+            from: samples(from, &from_table, count),
+            to: samples(to, &to_table, count),
+            tolerance,
+        }
+    }
+
+    /// Gets the points of the shape the fraction u of the way from the first
+    /// shape to the second, at equal distances along it
+    pub fn points_at(&self, u: f64) -> Vec<Point> {
+        let u = u.clamp(0.0, 1.0);
+        self.from
+            .iter()
+            .zip(&self.to)
+            .map(|(a, b)| *a + (*b - *a) * u)
+            .collect()
+    }
+
+    /// Gets the shape the fraction u of the way from the first shape to the
+    /// second as a path fitted through the blended points, or None if the
+    /// shapes collapse to a point.
+    pub fn shape_at(&self, u: f64) -> Option<BezierPath> {
+        BezierPath::fit(&self.points_at(u), self.tolerance, self.tolerance)
+    }
+}
+
+/// Gets the number of intervals at equal distances along the curve that
+/// keeps the chords within the tolerance of the curve. A chord of length c
+/// on a curve of curvature k strays from the curve by about
+///      e = c^2 * k / 8
+/// so the chords are kept no longer than sqrt(8 * tolerance / k) at the
+/// sharpest point of the curve, up to the limit on the number of intervals.
+fn intervals<C: Curve + ?Sized>(curve: &C, table: &ArcLengthTable, tolerance: f64) -> usize {
+    let k = curve
+        .flatten()
+        .into_iter()
+        .map(|t| curve.curvature_at(t).abs())
+        .fold(0.0, f64::max);
+    if k <= f64::EPSILON {
+        return MORPH_MIN_INTERVALS;
+    }
+    let chord = (8.0 * tolerance / k).sqrt();
+    ((table.length() / chord).ceil() as usize).clamp(MORPH_MIN_INTERVALS, MORPH_MAX_INTERVALS)
+}
+
+/// Gets the points at the ends of the intervals at equal distances along the
+/// curve, from start to end
+fn samples<C: Curve + ?Sized>(curve: &C, table: &ArcLengthTable, count: usize) -> Vec<Point> {
+    let length = table.length();
+    (0..=count)
+        .map(|i| curve.point_at_distance(table, length * i as f64 / count as f64))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        bezier::Bezier,
+        fixtures::{bezier, distance_to_curves, hump},
+    };
+
+    fn straight() -> Bezier {
+        bezier([(0.0, 0.0), (300.0, 0.0)], 0.01)
+    }
+
+    fn distance(path: &BezierPath, p: &Point) -> f64 {
+        distance_to_curves(path.segments(), p)
+    }
+
+    #[test]
+    fn test_ends() {
+        let (a, b) = (straight(), hump(0.01));
+        let morph = Morph::new(&a, &b, 0.01);
+        let start = morph.shape_at(0.0).unwrap();
+        let end = morph.shape_at(1.0).unwrap();
+        for i in 0..=100 {
+            let t = i as f64 / 100.0;
+            // the fitted shapes follow the originals
+            assert!(start.point_at(t).y.abs() < 0.02);
+            assert!(distance(&b, &end.point_at(t)) < 0.02);
+            // and the originals follow the fitted shapes
+            assert!(distance(&start, &a.point_at(t)) < 0.02);
+            assert!(distance(&end, &b.point_at(t)) < 0.02);
+        }
+    }
+
+    #[test]
+    fn test_intervals() {
+        // a straight shape needs only the fewest samples
+        let a = straight();
+        let table = a.arc_length_table(0.01);
+        assert_eq!(intervals(&a, &table, 0.01), MORPH_MIN_INTERVALS);
+        // a sharper shape or a finer tolerance needs more
+        let b = hump(0.01);
+        let coarse = intervals(&b, &b.arc_length_table(0.1), 0.1);
+        let fine = intervals(&b, &b.arc_length_table(0.01), 0.01);
+        assert!(coarse > MORPH_MIN_INTERVALS);
+        assert!(fine > coarse);
+        // a cusp can never be followed to the tolerance
+        let cusp = bezier(
+            [(0.0, 0.0), (100.0, 100.0), (0.0, 100.0), (100.0, 0.0)],
+            0.01,
+        );
+        let table = cusp.arc_length_table(0.01);
+        assert_eq!(intervals(&cusp, &table, 0.01), MORPH_MAX_INTERVALS);
+    }
+
+    #[test]
+    fn test_halfway() {
+        let (a, b) = (straight(), hump(0.01));
+        let morph = Morph::new(&a, &b, 0.01);
+        let points = morph.points_at(0.5);
+        assert_eq!(points[0], Point { x: 0.0, y: 0.0 });
+        assert_eq!(*points.last().unwrap(), Point { x: 300.0, y: 0.0 });
+        // the top of the hump is matched with the middle of the straight
+        let spacing = 300.0 / (points.len() - 1) as f64;
+        let middle = points.iter().max_by(|p, q| p.y.total_cmp(&q.y)).unwrap();
+        assert!((middle.x - 150.0).abs() < spacing);
+        assert!((middle.y - 50.0).abs() < 0.01);
+
+        let shape = morph.shape_at(0.5).unwrap();
+        let length = shape.length();
+        assert!(length > a.length() && length < b.length());
+    }
+}
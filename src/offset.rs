@@ -0,0 +1,302 @@
+use std::fmt;
+
+use crate::{
+    bezier::{segment_distance, Bezier},
+    curve::Curve,
+    geometry::Point,
+    projection::refine_nearest,
+};
+
+/// Number of samples used to search for cusps on an offset curve
+const CUSP_SAMPLES: usize = 128;
+/// Bisection iterations used to locate a cusp once it is bracketed
+const CUSP_ITERATIONS: usize = 24;
+/// Limits the subdivision depth when approximating an offset curve. Pieces
+/// at this depth that are still not within tolerance are reported as an
+/// error.
+const OFFSET_MAX_DEPTH: u32 = 12;
+/// Minimum number of subdivisions so that features between the end points of
+/// the curve are not missed by the error test.
+const OFFSET_MIN_DEPTH: u32 = 2;
+/// Parameters of the interior points used to measure the approximation error
+const ERROR_SAMPLES: [f64; 5] = [0.1, 0.25, 0.5, 0.75, 0.9];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OffsetError {
+    /// The offset distance exceeds the radius of curvature at the listed
+    /// curve parameters, so the offset curve folds back on itself and forms
+    /// a cusp at each of them.
+    Cusp(Vec<f64>),
+    /// The offset distance exceeds the radius of curvature along the whole
+    /// curve and the offset curve runs backwards.
+    Reversed,
+    /// Spans of 1/4096 of the curve are still not within tolerance of the
+    /// offset, such as close to a cusp of the offset. Holds the largest
+    /// error of the approximation.
+    NotConverged { error: f64 },
+}
+
+impl fmt::Display for OffsetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OffsetError::Cusp(t) => write!(f, "offset curve has cusps at t = {:?}", t),
+            OffsetError::Reversed => write!(f, "offset curve is reversed"),
+            OffsetError::NotConverged { error } => {
+                write!(f, "offset approximation is {} from the offset curve", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OffsetError {}
+
+/// Offset curves, such as the rails either side of a track centerline.
+pub trait Offset: Curve {
+    /// Gets the point offset from the curve at parameter t by the signed
+    /// distance along the left normal. Positive distances are to the left of
+    /// the direction of travel.
+    fn offset_point_at(&self, t: f64, distance: f64) -> Point {
+        self.point_at(t) + self.normal_at(t) * distance
+    }
+
+    /// Gets the parameters of the cusps on the offset of the curve at the
+    /// signed distance.
+    ///
+    /// The offset O(t) = C(t) + d * N(t) has the derivative
+    ///      O'(t) = |C'(t)| * (1 - d * k(t)) * T(t)
+    /// where k is the signed curvature. The offset reverses direction, and
+    /// forms a cusp, wherever 1 - d * k(t) changes sign.
+    fn offset_cusps(&self, distance: f64) -> Result<Vec<f64>, OffsetError> {
+        let scale = |t: f64| 1.0 - distance * self.curvature_at(t);
+        let mut cusps = Vec::new();
+        let mut reversed = 0;
+        let mut t0 = 0.0;
+        let mut s0 = scale(t0);
+        for i in 1..=CUSP_SAMPLES {
+            let t1 = i as f64 / CUSP_SAMPLES as f64;
+            let s1 = scale(t1);
+            if s1 < 0.0 {
+                reversed += 1;
+            }
+            if (s0 < 0.0) != (s1 < 0.0) {
+                let (mut lo, mut hi) = (t0, t1);
+                for _ in 0..CUSP_ITERATIONS {
+                    let mid = (lo + hi) * 0.5;
+                    if (scale(mid) < 0.0) == (s0 < 0.0) {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                cusps.push((lo + hi) * 0.5);
+            }
+            t0 = t1;
+            s0 = s1;
+        }
+        if cusps.is_empty() && reversed == CUSP_SAMPLES {
+            return Err(OffsetError::Reversed);
+        }
+        Ok(cusps)
+    }
+
+    /// Gets the offset of the curve at the signed distance as a polyline
+    /// that stays within tolerance of the true offset curve.
+    ///
+    /// The curve is split in halves at most 12 times, and an offset that
+    /// is not within tolerance by then is reported as not converged.
+    fn offset_polyline(&self, distance: f64, tolerance: f64) -> Result<Vec<Point>, OffsetError> {
+        check_offset(self, distance)?;
+        let mut points = vec![self.offset_point_at(0.0, distance)];
+        let error = flatten_offset(self, 0.0, 1.0, distance, tolerance, 0, &mut points);
+        check_error(error, tolerance)?;
+        Ok(points)
+    }
+
+    /// Gets the offset of the curve at the signed distance as a chain of
+    /// cubic beziers that stays within tolerance of the true offset curve.
+    ///
+    /// As with the polyline, an offset that is not within tolerance after
+    /// 12 splits is reported as not converged.
+    fn offset_curves(&self, distance: f64, tolerance: f64) -> Result<Vec<Bezier>, OffsetError> {
+        check_offset(self, distance)?;
+        let mut curves = Vec::new();
+        let error = approximate_offset(self, 0.0, 1.0, distance, tolerance, 0, &mut curves);
+        check_error(error, tolerance)?;
+        Ok(curves)
+    }
+}
+
+impl<C: Curve + ?Sized> Offset for C {}
+
+fn check_offset<C: Curve + ?Sized>(curve: &C, distance: f64) -> Result<(), OffsetError> {
+    let cusps = curve.offset_cusps(distance)?;
+    if !cusps.is_empty() {
+        return Err(OffsetError::Cusp(cusps));
+    }
+    Ok(())
+}
+
+fn check_error(error: f64, tolerance: f64) -> Result<(), OffsetError> {
+    if error > tolerance {
+        return Err(OffsetError::NotConverged { error });
+    }
+    Ok(())
+}
+
+/// Flattens the offset between t0 and t1 and returns the largest error of
+/// the segments that were kept.
+fn flatten_offset<C: Curve + ?Sized>(
+    curve: &C,
+    t0: f64,
+    t1: f64,
+    distance: f64,
+    tolerance: f64,
+    depth: u32,
+    points: &mut Vec<Point>,
+) -> f64 {
+    let start = curve.offset_point_at(t0, distance);
+    let end = curve.offset_point_at(t1, distance);
+    let error = ERROR_SAMPLES
+        .iter()
+        .map(|u| {
+            let p = curve.offset_point_at(t0 + (t1 - t0) * u, distance);
+            segment_distance(&p, &start, &end)
+        })
+        .fold(0.0, f64::max);
+    if depth >= OFFSET_MAX_DEPTH || (depth >= OFFSET_MIN_DEPTH && error <= tolerance) {
+        points.push(end);
+        error
+    } else {
+        let mid = (t0 + t1) * 0.5;
+        let first = flatten_offset(curve, t0, mid, distance, tolerance, depth + 1, points);
+        let second = flatten_offset(curve, mid, t1, distance, tolerance, depth + 1, points);
+        first.max(second)
+    }
+}
+
+/// Approximates the offset between t0 and t1 with the cubic hermite
+/// curve that matches the position, direction and speed of the true
+/// offset at both ends, subdividing until it is within tolerance. Returns
+/// the largest error of the curves that were kept.
+fn approximate_offset<C: Curve + ?Sized>(
+    curve: &C,
+    t0: f64,
+    t1: f64,
+    distance: f64,
+    tolerance: f64,
+    depth: u32,
+    curves: &mut Vec<Bezier>,
+) -> f64 {
+    let handle = |t: f64| {
+        let speed = curve.derivative_at(t).magnitude();
+        let scale = 1.0 - distance * curve.curvature_at(t);
+        curve.tangent_at(t) * (speed * scale * (t1 - t0) / 3.0)
+    };
+    let start = curve.offset_point_at(t0, distance);
+    let end = curve.offset_point_at(t1, distance);
+    let candidate = Bezier::new_with_ctrl_point(
+        [start, start + handle(t0), end - handle(t1), end],
+        curve.tolerance(),
+    );
+    let error = ERROR_SAMPLES
+        .iter()
+        .map(|u| {
+            let p = candidate.point_at(*u);
+            let t = refine_nearest(curve, &p, t0 + (t1 - t0) * u, t0, t1);
+            (p.distance(&curve.point_at(t)) - distance.abs()).abs()
+        })
+        .fold(0.0, f64::max);
+    if depth >= OFFSET_MAX_DEPTH || (depth >= OFFSET_MIN_DEPTH && error <= tolerance) {
+        curves.push(candidate);
+        error
+    } else {
+        let mid = (t0 + t1) * 0.5;
+        let first = approximate_offset(curve, t0, mid, distance, tolerance, depth + 1, curves);
+        let second = approximate_offset(curve, mid, t1, distance, tolerance, depth + 1, curves);
+        first.max(second)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::quarter_circle;
+
+    #[test]
+    fn test_offset_polyline() {
+        let b = quarter_circle(0.25);
+        // the left normal of a counter-clockwise arc points at the center
+        let points = b.offset_polyline(10.0, 0.1).unwrap();
+        assert!(points.len() > 4);
+        assert!((points[0].x - 90.0).abs() < 0.01);
+        for p in points.iter() {
+            let r = p.magnitude();
+            assert!((r - 90.0).abs() < 0.2, "radius {}", r);
+        }
+    }
+
+    #[test]
+    fn test_offset_curves() {
+        let b = quarter_circle(0.25);
+        let curves = b.offset_curves(-10.0, 0.05).unwrap();
+        assert!(!curves.is_empty());
+        assert_eq!(curves[0].control_points()[0], Point { x: 110.0, y: 0.0 });
+        for c in curves.iter() {
+            for i in 0..=10 {
+                let r = c.point_at(i as f64 / 10.0).magnitude();
+                assert!((r - 110.0).abs() < 0.2, "radius {}", r);
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_cusp() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 50.0, y: 0.0 },
+                Point { x: 100.0, y: 50.0 },
+                Point { x: 100.0, y: 100.0 },
+            ],
+            0.25,
+        );
+        assert!(b.offset_cusps(10.0).unwrap().is_empty());
+        match b.offset_polyline(80.0, 0.1) {
+            Err(OffsetError::Cusp(t)) => assert_eq!(t.len(), 2),
+            r => panic!("expected cusps, got {:?}", r),
+        }
+        assert_eq!(
+            quarter_circle(0.25).offset_curves(150.0, 0.1).unwrap_err(),
+            OffsetError::Reversed
+        );
+    }
+
+    #[test]
+    fn test_offset_tight_radius() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 50.0, y: 0.0 },
+                Point { x: 100.0, y: 50.0 },
+                Point { x: 100.0, y: 100.0 },
+            ],
+            0.25,
+        );
+        // just inside the tightest radius the offset turns sharply
+        let d = b.min_radius().radius * 0.99;
+        for c in b.offset_curves(d, 0.1).unwrap() {
+            for i in 0..=50 {
+                let p = c.point_at(i as f64 / 50.0);
+                assert!((b.distance_to(&p) - d).abs() <= 0.1);
+            }
+        }
+        for p in b.offset_polyline(d, 0.1).unwrap() {
+            assert!((b.distance_to(&p) - d).abs() <= 0.1);
+        }
+        // a tolerance that cannot be met at the depth limit is reported
+        match b.offset_polyline(d, 1e-9) {
+            Err(OffsetError::NotConverged { error }) => assert!(error > 1e-9),
+            r => panic!("expected no convergence, got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use crate::{
+    bezier::Bezier,
+    curve::Curve,
+    geometry::{Affine, Point, Transform},
+};
+
+/// Joint ends further apart than this in layout units are not connected
+const POSITION_EPSILON: f64 = 1e-3;
+/// Joints with a larger sine of the angle between the tangents are kinked
+const ANGLE_EPSILON: f64 = 1e-4;
+/// Relative difference of the curvature allowed either side of a G2 joint
+const CURVATURE_EPSILON: f64 = 1e-3;
+/// Curvatures closer than this are equal, so that straight track meets
+/// straight track with G2 continuity.
+const CURVATURE_FLOOR: f64 = 1e-6;
+
+/// The geometric continuity of a path at a joint, in increasing order of
+/// smoothness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Continuity {
+    /// The segment ends do not meet
+    Gap,
+    /// The segment ends meet with a kink
+    G0,
+    /// The segment ends meet with the same direction
+    G1,
+    /// The segment ends meet with the same direction and curvature
+    G2,
+}
+
+/// How the handles either side of a joint follow when one of them moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JointMode {
+    /// Handles move independently and the joint may kink
+    Free,
+    /// The handles stay collinear with the joint, keeping their lengths
+    G1,
+    /// The handles stay collinear with the joint and their lengths keep the
+    /// first derivative continuous across the joint.
+    C1,
+}
+
+/// A path of bezier segments joined end to end, such as a mainline made of
+/// several pieces of flex track.
+///
+/// Joint j is where segment j ends and segment j + 1 starts. As a `Curve`
+/// the path maps t onto the segments in equal steps, so segment i spans
+/// i / n <= t <= (i + 1) / n of the n segments.
+#[derive(Debug, Clone)]
+pub struct BezierPath {
+    segments: Vec<Bezier>,
+    mode: JointMode,
+}
+
+impl BezierPath {
+    /// Creates a path with a single segment that keeps its joints G1
+    /// continuous as it is edited.
+    pub fn new(first: Bezier) -> Self {
+        BezierPath {
+            segments: vec![first],
+            mode: JointMode::G1,
+        }
+    }
+
+    /// Appends the segment to the end of the path. The first control point
+    /// of the segment is moved onto the end of the path, and the new joint is
+    /// made smooth unless the joint mode is free.
+    pub fn push(&mut self, mut segment: Bezier) {
+        let end = self.end_point();
+        segment.set_control_point(0, end);
+        self.segments.push(segment);
+        self.align(self.segments.len() - 2, true);
+    }
+
+    pub fn segments(&self) -> &[Bezier] {
+        &self.segments
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn joint_mode(&self) -> JointMode {
+        self.mode
+    }
+
+    pub fn set_joint_mode(&mut self, mode: JointMode) {
+        self.mode = mode;
+    }
+
+    /// Gets the position of joint j
+    pub fn joint(&self, j: usize) -> Point {
+        self.segments[j + 1].control_points()[0]
+    }
+
+    pub fn joint_count(&self) -> usize {
+        self.segments.len() - 1
+    }
+
+    /// Gets the continuity of the path at joint j
+    pub fn continuity_at(&self, j: usize) -> Continuity {
+        let (a, b) = (&self.segments[j], &self.segments[j + 1]);
+        if a.point_at(1.0).distance(&b.point_at(0.0)) > POSITION_EPSILON {
+            return Continuity::Gap;
+        }
+        let (ta, tb) = (a.tangent_at(1.0), b.tangent_at(0.0));
+        if ta.cross(&tb).abs() > ANGLE_EPSILON || ta.dot(&tb) <= 0.0 {
+            return Continuity::G0;
+        }
+        let (ka, kb) = (a.curvature_at(1.0), b.curvature_at(0.0));
+        if (ka - kb).abs() > CURVATURE_EPSILON * ka.abs().max(kb.abs()) + CURVATURE_FLOOR {
+            return Continuity::G1;
+        }
+        Continuity::G2
+    }
+
+    /// Gets the lowest continuity of all of the joints of the path
+    pub fn continuity(&self) -> Continuity {
+        (0..self.joint_count())
+            .map(|j| self.continuity_at(j))
+            .min()
+            .unwrap_or(Continuity::G2)
+    }
+
+    /// Gets the signed angle in radians the path turns through at joint j,
+    /// which is 0.0 at a smooth joint.
+    pub fn kink_angle(&self, j: usize) -> f64 {
+        let ta = self.segments[j].tangent_at(1.0);
+        let tb = self.segments[j + 1].tangent_at(0.0);
+        ta.cross(&tb).atan2(ta.dot(&tb))
+    }
+
+    /// Gets the joints where the path kinks or has a gap
+    pub fn kinks(&self) -> Vec<usize> {
+        (0..self.joint_count())
+            .filter(|j| self.continuity_at(*j) < Continuity::G1)
+            .collect()
+    }
+
+    /// Moves control point index of segment i to p.
+    ///
+    /// A joint moves the end points of both segments that meet there, and
+    /// unless the joint mode is free the handles either side of it move
+    /// along with it. Moving a handle next to a joint turns the handle on
+    /// the other side of the joint to keep the joint smooth.
+    pub fn move_control_point(&mut self, i: usize, index: usize, p: Point) {
+        let last = self.segments[i].degree();
+        if index == 0 && i > 0 {
+            self.move_joint(i - 1, p);
+        } else if index == last && i + 1 < self.segments.len() {
+            self.move_joint(i, p);
+        } else {
+            self.segments[i].set_control_point(index, p);
+            if index == 1 && i > 0 {
+                self.align(i - 1, false);
+            }
+            if index + 1 == last && i + 1 < self.segments.len() {
+                self.align(i, true);
+            }
+        }
+    }
+
+    fn move_joint(&mut self, j: usize, p: Point) {
+        let delta = p - self.joint(j);
+        let last = self.segments[j].degree();
+        self.segments[j].set_control_point(last, p);
+        self.segments[j + 1].set_control_point(0, p);
+        if self.mode != JointMode::Free {
+            for (segment, index) in [(j, last - 1), (j + 1, 1)] {
+                let s = &mut self.segments[segment];
+                if s.degree() >= 2 {
+                    let q = s.control_points()[index];
+                    s.set_control_point(index, q + delta);
+                }
+            }
+        }
+        self.align(j, true);
+    }
+
+    /// Turns one of the handles either side of joint j to match the other
+    /// according to the joint mode. The handle before the joint is kept when
+    /// keep_before is set. A straight segment cannot turn without moving
+    /// its far end, so the handle on a straight segment is always kept.
+    ///
+    /// The derivatives either side of the joint are n_a * (J - P) and
+    /// n_b * (Q - J) for the handles P and Q of segments of degree n_a and
+    /// n_b, so C1 continuity scales the handle lengths by n_a / n_b.
+    fn align(&mut self, j: usize, keep_before: bool) {
+        if self.mode == JointMode::Free {
+            return;
+        }
+        let na = self.segments[j].degree();
+        let nb = self.segments[j + 1].degree();
+        if na == 1 && nb == 1 {
+            return;
+        }
+        let keep_before = na == 1 || (nb != 1 && keep_before);
+        let joint = self.joint(j);
+        let before = joint - self.segments[j].control_points()[na - 1];
+        let after = self.segments[j + 1].control_points()[1] - joint;
+        let ratio = na as f64 / nb as f64;
+        if keep_before {
+            let Some(direction) = before.normalize() else {
+                return;
+            };
+            let length = match self.mode {
+                JointMode::C1 => before.magnitude() * ratio,
+                _ => after.magnitude(),
+            };
+            self.segments[j + 1].set_control_point(1, joint + direction * length);
+        } else {
+            let Some(direction) = after.normalize() else {
+                return;
+            };
+            let length = match self.mode {
+                JointMode::C1 => after.magnitude() / ratio,
+                _ => before.magnitude(),
+            };
+            self.segments[j].set_control_point(na - 1, joint - direction * length);
+        }
+    }
+
+    /// Gets the point at the end of the path
+    fn end_point(&self) -> Point {
+        *self
+            .segments
+            .last()
+            .unwrap()
+            .control_points()
+            .last()
+            .unwrap()
+    }
+
+    /// Gets the segment containing t and the parameter on that segment
+    fn locate(&self, t: f64) -> (usize, f64) {
+        let n = self.segments.len();
+        let s = t.clamp(0.0, 1.0) * n as f64;
+        let i = (s.floor() as usize).min(n - 1);
+        (i, s - i as f64)
+    }
+}
+
+impl Curve for BezierPath {
+    fn point_at(&self, t: f64) -> Point {
+        let (i, u) = self.locate(t);
+        self.segments[i].point_at(u)
+    }
+
+    fn derivative_at(&self, t: f64) -> Point {
+        let (i, u) = self.locate(t);
+        self.segments[i].derivative_at(u) * self.segments.len() as f64
+    }
+
+    fn second_derivative_at(&self, t: f64) -> Point {
+        let (i, u) = self.locate(t);
+        let n = self.segments.len() as f64;
+        self.segments[i].second_derivative_at(u) * (n * n)
+    }
+
+    fn flatten(&self) -> Vec<f64> {
+        let n = self.segments.len() as f64;
+        let mut params = vec![0.0];
+        for (i, segment) in self.segments.iter().enumerate() {
+            params.extend(
+                segment
+                    .flatten()
+                    .into_iter()
+                    .skip(1)
+                    .map(|u| (i as f64 + u) / n),
+            );
+        }
+        params
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.segments
+            .iter()
+            .map(|s| s.tolerance())
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Gets the arc length of the path between the parameters t0 and t1 as
+    /// the sum of the lengths on each segment, since the speed of the path
+    /// may jump at the joints.
+    fn arc_length(&self, t0: f64, t1: f64) -> f64 {
+        let n = self.segments.len() as f64;
+        self.segments
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| {
+                let u0 = (t0 * n - i as f64).clamp(0.0, 1.0);
+                let u1 = (t1 * n - i as f64).clamp(0.0, 1.0);
+                segment.arc_length(u0, u1)
+            })
+            .sum()
+    }
+}
+
+impl Transform for BezierPath {
+    fn transform(&mut self, m: &Affine) {
+        for segment in self.segments.iter_mut() {
+            segment.transform(m);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::bezier;
+
+    fn path() -> BezierPath {
+        let mut path = BezierPath::new(bezier(
+            [(0.0, 0.0), (100.0, 0.0), (200.0, 0.0), (300.0, 0.0)],
+            0.1,
+        ));
+        path.set_joint_mode(JointMode::Free);
+        path.push(bezier(
+            [(300.0, 0.0), (400.0, 0.0), (500.0, 100.0), (500.0, 200.0)],
+            0.1,
+        ));
+        path
+    }
+
+    #[test]
+    fn test_continuity() {
+        let mut path = path();
+        assert_eq!(path.joint_count(), 1);
+        assert_eq!(path.continuity_at(0), Continuity::G1);
+        assert!(path.kinks().is_empty());
+
+        // straight into straight has matching zero curvature
+        let mut straight = BezierPath::new(bezier(
+            [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)],
+            0.1,
+        ));
+        straight.push(bezier(
+            [(3.0, 0.0), (4.0, 0.0), (5.0, 0.0), (6.0, 0.0)],
+            0.1,
+        ));
+        assert_eq!(straight.continuity(), Continuity::G2);
+
+        path.move_control_point(1, 1, Point { x: 400.0, y: 50.0 });
+        assert_eq!(path.continuity_at(0), Continuity::G0);
+        assert_eq!(path.kinks(), vec![0]);
+        assert!(path.kink_angle(0) > 0.0);
+
+        // pushing moves the start of the segment onto the end of the path
+        path.push(bezier(
+            [(0.0, 0.0), (0.0, 0.0), (0.0, 0.0), (0.0, 0.0)],
+            0.1,
+        ));
+        assert_eq!(path.joint(1), Point { x: 500.0, y: 200.0 });
+    }
+
+    #[test]
+    fn test_edit_g1() {
+        let mut path = path();
+        path.set_joint_mode(JointMode::G1);
+        path.move_control_point(1, 1, Point { x: 400.0, y: 50.0 });
+        assert_eq!(path.continuity_at(0), Continuity::G1);
+        // the handle before the joint keeps its length
+        let handle = path.segments()[0].control_points()[2];
+        assert!((handle.distance(&path.joint(0)) - 100.0).abs() < 1e-3);
+
+        // moving the joint carries both handles
+        path.move_control_point(0, 3, Point { x: 300.0, y: 10.0 });
+        assert_eq!(path.segments()[1].control_points()[0], path.joint(0));
+        assert_eq!(path.continuity_at(0), Continuity::G1);
+        assert_eq!(path.segments()[0].control_points()[2].y, handle.y + 10.0);
+    }
+
+    #[test]
+    fn test_edit_c1() {
+        let mut path = path();
+        path.set_joint_mode(JointMode::C1);
+        path.push(Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 550.0, y: 300.0 },
+                Point { x: 600.0, y: 300.0 },
+            ],
+            0.1,
+        ));
+        path.move_control_point(1, 2, Point { x: 480.0, y: 100.0 });
+        for j in 0..path.joint_count() {
+            let a = path.segments()[j].derivative_at(1.0);
+            let b = path.segments()[j + 1].derivative_at(0.0);
+            assert!((a - b).magnitude() < 1e-2);
+        }
+
+        // a straight segment keeps its direction and the curve follows it
+        let mut path = BezierPath::new(Bezier::new_with_ctrl_point(
+            [Point { x: 0.0, y: 0.0 }, Point { x: 100.0, y: 0.0 }],
+            0.1,
+        ));
+        path.set_joint_mode(JointMode::C1);
+        path.push(bezier(
+            [(0.0, 0.0), (150.0, 50.0), (200.0, 100.0), (200.0, 200.0)],
+            0.1,
+        ));
+        assert_eq!(path.segments()[1].control_points()[1].y, 0.0);
+        assert_eq!(path.continuity_at(0), Continuity::G1);
+    }
+
+    #[test]
+    fn test_curve() {
+        let path = path();
+        let length = path.segments()[0].length() + path.segments()[1].length();
+        assert!((path.length() - length).abs() < 1e-2);
+        assert_eq!(path.point_at(0.5), path.joint(0));
+        assert_eq!(path.point_at(1.0), Point { x: 500.0, y: 200.0 });
+        let params = path.flatten();
+        assert_eq!(params[0], 0.0);
+        assert_eq!(*params.last().unwrap(), 1.0);
+        assert!(params.windows(2).all(|w| w[0] < w[1]));
+    }
+}
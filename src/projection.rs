@@ -0,0 +1,158 @@
+use crate::{
+    bezier::{bernstein_roots, binomial, Bezier},
+    curve::Curve,
+    geometry::Point,
+};
+
+/// Newton iterations used to refine the closest point in an interval
+const PROJECTION_ITERATIONS: usize = 8;
+
+/// The point on a curve closest to a query point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    pub t: f64,
+    pub point: Point,
+    /// Distance from the query point to the curve
+    pub distance: f64,
+    /// Distance along the curve from the start to the closest point
+    pub arc_length: f64,
+}
+
+impl Bezier {
+    /// Gets the point on the curve closest to p.
+    ///
+    /// The closest point is either an end point or a root of
+    ///      f(t) = (B(t) - p) . B'(t) = 0
+    /// With B(t) - p of degree n with the control points Q(i) = P(i) - p, and
+    /// B'(t) of degree n - 1 with the control points D(j), f is a polynomial
+    /// of degree 2n - 1 with the Bernstein coefficients
+    ///      c(k) = sum over i + j = k of
+    ///             C(n, i) * C(n - 1, j) / C(2n - 1, k) * Q(i) . D(j)
+    /// whose roots are all found, and each is refined with Newton
+    /// iterations before the overall minimum is kept.
+    pub fn project(&self, p: &Point) -> Projection {
+        let ctrl_point = self.control_points();
+        let n = self.degree();
+        let m = n - 1;
+        let derivative: Vec<Point> = ctrl_point
+            .windows(2)
+            .map(|w| (w[1] - w[0]) * n as f64)
+            .collect();
+        let mut c = vec![0.0; n + m + 1];
+        for (i, q) in ctrl_point.iter().enumerate() {
+            for (j, d) in derivative.iter().enumerate() {
+                c[i + j] += binomial(n, i) * binomial(m, j) * (*q - *p).dot(d);
+            }
+        }
+        for (k, c) in c.iter_mut().enumerate() {
+            *c /= binomial(n + m, k);
+        }
+
+        let (best_t, best_distance) = [0.0, 1.0]
+            .into_iter()
+            .chain(
+                bernstein_roots(&c)
+                    .into_iter()
+                    .map(|t| refine_nearest(self, p, t, 0.0, 1.0)),
+            )
+            .map(|t| (t, self.point_at(t).distance(p)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        Projection {
+            t: best_t,
+            point: self.point_at(best_t),
+            distance: best_distance,
+            arc_length: self.length_table().distance_at(best_t),
+        }
+    }
+
+    /// Gets the distance from p to the closest point on the curve
+    pub fn distance_to(&self, p: &Point) -> f64 {
+        self.project(p).distance
+    }
+}
+
+/// Refines the parameter of the point on the curve closest to p with
+/// Newton iterations starting from t and limited to [lo, hi].
+pub(crate) fn refine_nearest<C: Curve + ?Sized>(
+    curve: &C,
+    p: &Point,
+    t: f64,
+    lo: f64,
+    hi: f64,
+) -> f64 {
+    let mut t = t;
+    for _ in 0..PROJECTION_ITERATIONS {
+        let diff = curve.point_at(t) - *p;
+        let d1 = curve.derivative_at(t);
+        let d2 = curve.second_derivative_at(t);
+        let denominator = d1.dot(&d1) + diff.dot(&d2);
+        if denominator.abs() <= f64::EPSILON {
+            break;
+        }
+        let next = (t - diff.dot(&d1) / denominator).clamp(lo, hi);
+        // keep the previous estimate if the step moved away from p
+        if curve.point_at(next).distance(p) > diff.magnitude() {
+            break;
+        }
+        t = next;
+    }
+    t
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::arch;
+
+    const TEST_EPSILON: f64 = 0.01;
+
+    #[test]
+    fn test_project() {
+        let b = arch(0.25);
+        let top = b.point_at(0.5);
+        let projection = b.project(&Point { x: 50.0, y: 100.0 });
+        assert!((projection.t - 0.5).abs() < TEST_EPSILON);
+        assert!(projection.point.distance(&top) < TEST_EPSILON);
+        assert!((projection.distance - 25.0).abs() < TEST_EPSILON);
+        assert!((projection.arc_length - b.arc_length(0.0, 1.0) * 0.5).abs() < 0.1);
+        // the distance maps back to the closest point through the same table
+        let p = b.point_at_distance(b.length_table(), projection.arc_length);
+        assert!(p.distance(&projection.point) < 1e-6);
+
+        // points beyond the ends project onto the end points
+        let projection = b.project(&Point { x: -10.0, y: -10.0 });
+        assert_eq!(projection.t, 0.0);
+        assert_eq!(projection.arc_length, 0.0);
+        assert_eq!(b.distance_to(&Point { x: 100.0, y: -10.0 }), 10.0);
+
+        // a line projects onto the foot of the perpendicular
+        let line = Bezier::new_with_ctrl_point(
+            [Point { x: 0.0, y: 0.0 }, Point { x: 100.0, y: 0.0 }],
+            0.25,
+        );
+        let projection = line.project(&Point { x: 30.0, y: 40.0 });
+        assert!((projection.t - 0.3).abs() < 1e-9);
+        assert!((projection.distance - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_project_global() {
+        // a looping curve that passes the query point at two distances
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 150.0, y: 100.0 },
+                Point { x: -50.0, y: 100.0 },
+                Point { x: 100.0, y: 0.0 },
+            ],
+            0.25,
+        );
+        let p = Point { x: 50.0, y: 10.0 };
+        let projection = b.project(&p);
+        for i in 0..=1000 {
+            let d = b.point_at(i as f64 / 1000.0).distance(&p);
+            assert!(projection.distance <= d + TEST_EPSILON);
+        }
+    }
+}
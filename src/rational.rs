@@ -0,0 +1,347 @@
+use std::{cell::OnceCell, f64::consts::PI};
+
+use crate::{
+    bezier::{
+        bernstein, bernstein_derivative, segment_distance, split_ctrl_points, FLATTEN_MAX_DEPTH,
+    },
+    curve::{ArcLengthTable, Curve},
+    geometry::{Affine, Point, Transform},
+};
+
+/// A rational Bezier curve
+///
+///            sum(w_i * P_i * B_i(t))
+///     C(t) = -----------------------
+///              sum(w_i * B_i(t))
+/// where B_i are the Bernstein basis polynomials and w_i the positive
+/// weights of the control points P_i. Unlike a polynomial Bezier curve a
+/// rational quadratic represents a circular arc exactly, which sectional
+/// track pieces need so that a loop of them closes without gaps.
+#[derive(Debug, Clone)]
+pub struct RationalBezier {
+    ctrl_point: Vec<Point>,
+    weight: Vec<f64>,
+    tolerance: f64,
+    /// Arc length table built to the tolerance on first use and cleared
+    /// whenever the curve changes
+    table: OnceCell<ArcLengthTable>,
+}
+
+impl RationalBezier {
+    /// Creates a rational Bezier curve from the control points and their
+    /// weights. There must be a positive weight for each control point.
+    pub fn new(
+        ctrl_point: impl Into<Vec<Point>>,
+        weight: impl Into<Vec<f64>>,
+        tolerance: f64,
+    ) -> Self {
+        let ctrl_point = ctrl_point.into();
+        let weight = weight.into();
+        assert!(ctrl_point.len() >= 2);
+        assert_eq!(ctrl_point.len(), weight.len());
+        assert!(weight.iter().all(|w| *w > 0.0));
+        RationalBezier {
+            ctrl_point,
+            weight,
+            tolerance,
+            table: OnceCell::new(),
+        }
+    }
+
+    /// Creates the circular arc around center starting at the angle in
+    /// radians and turning through sweep radians, counter-clockwise when the
+    /// sweep is positive, or None unless the sweep is less than half of a
+    /// turn.
+    pub fn arc(
+        center: Point,
+        radius: f64,
+        start_angle: f64,
+        sweep: f64,
+        tolerance: f64,
+    ) -> Option<Self> {
+        let start = center
+            + Point {
+                x: start_angle.cos(),
+                y: start_angle.sin(),
+            } * radius;
+        let heading = Point {
+            x: -start_angle.sin(),
+            y: start_angle.cos(),
+        } * sweep.signum();
+        Self::arc_from(start, heading, radius, sweep, tolerance)
+    }
+
+    /// Creates the circular arc of the radius that starts at start heading
+    /// in the direction and turns through sweep radians, to the left when
+    /// the sweep is positive, or None unless the sweep is less than half of
+    /// a turn.
+    ///
+    /// The middle control point is where the end tangents meet, and its
+    /// weight is the cosine of half of the sweep. At half of a turn the end
+    /// tangents are parallel and never meet, so longer arcs are built from
+    /// several pieces.
+    pub fn arc_from(
+        start: Point,
+        direction: Point,
+        radius: f64,
+        sweep: f64,
+        tolerance: f64,
+    ) -> Option<Self> {
+        if sweep.abs() >= PI {
+            return None;
+        }
+        let heading = direction.normalize().unwrap_or(Point { x: 1.0, y: 0.0 });
+        let (sin, cos) = sweep.sin_cos();
+        let end_heading = Point {
+            x: heading.x * cos - heading.y * sin,
+            y: heading.x * sin + heading.y * cos,
+        };
+        let reach = radius * (sweep.abs() * 0.5).tan();
+        let corner = start + heading * reach;
+        Some(RationalBezier::new(
+            [start, corner, corner + end_heading * reach],
+            [1.0, (sweep * 0.5).cos(), 1.0],
+            tolerance,
+        ))
+    }
+
+    pub fn control_points(&self) -> &[Point] {
+        &self.ctrl_point
+    }
+
+    pub fn weights(&self) -> &[f64] {
+        &self.weight
+    }
+
+    pub fn degree(&self) -> usize {
+        self.ctrl_point.len() - 1
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: f64) {
+        if self.tolerance != tolerance {
+            self.tolerance = tolerance;
+            self.table = OnceCell::new();
+        }
+    }
+
+    /// Gets the arc length table of the curve, measured to the tolerance.
+    /// The table is built once and kept until the curve changes.
+    pub fn length_table(&self) -> &ArcLengthTable {
+        self.table
+            .get_or_init(|| self.arc_length_table(self.tolerance))
+    }
+
+    /// Gets the control points multiplied by their weights, the numerator
+    /// coefficients of the curve.
+    fn weighted_points(&self) -> Vec<Point> {
+        self.ctrl_point
+            .iter()
+            .zip(&self.weight)
+            .map(|(p, w)| *p * *w)
+            .collect()
+    }
+
+    /// Gets the numerator A(t) and denominator w(t) of the curve and their
+    /// derivatives up to the second at parameter t.
+    fn homogeneous_at(&self, t: f64) -> ([Point; 3], [f64; 3]) {
+        let t = t.clamp(0.0, 1.0);
+        let a = self.weighted_points();
+        (
+            [0, 1, 2].map(|order| bernstein_derivative(&a, order, t)),
+            [0, 1, 2].map(|order| bernstein_derivative(&self.weight, order, t)),
+        )
+    }
+}
+
+impl Curve for RationalBezier {
+    fn point_at(&self, t: f64) -> Point {
+        let t = t.clamp(0.0, 1.0);
+        let n = self.degree();
+        let a = bernstein(n, t, |i| self.ctrl_point[i] * self.weight[i]);
+        a * (1.0 / bernstein(n, t, |i| self.weight[i]))
+    }
+
+    /// Gets the first derivative of the curve at parameter t. With C = A / w
+    ///     C' = (A' - C * w') / w
+    fn derivative_at(&self, t: f64) -> Point {
+        let (a, w) = self.homogeneous_at(t);
+        let c = a[0] * (1.0 / w[0]);
+        (a[1] - c * w[1]) * (1.0 / w[0])
+    }
+
+    /// Gets the second derivative of the curve at parameter t. With C = A / w
+    ///     C'' = (A'' - 2 * C' * w' - C * w'') / w
+    fn second_derivative_at(&self, t: f64) -> Point {
+        let (a, w) = self.homogeneous_at(t);
+        let c = a[0] * (1.0 / w[0]);
+        let d1 = (a[1] - c * w[1]) * (1.0 / w[0]);
+        (a[2] - d1 * (2.0 * w[1]) - c * w[2]) * (1.0 / w[0])
+    }
+
+    /// Gets the parameters of the vertices of the flattened curve. With
+    /// positive weights the curve lies within the convex hull of its control
+    /// points, so it is split in homogeneous form until the hull is flat.
+    fn flatten(&self) -> Vec<f64> {
+        let mut params = vec![0.0];
+        flatten_rational(
+            &self.weighted_points(),
+            &self.weight,
+            0.0,
+            1.0,
+            self.tolerance,
+            0,
+            &mut params,
+        );
+        params
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Gets the total arc length of the curve from the cached arc length
+    /// table, so that the length agrees with the distance lookups.
+    fn length(&self) -> f64 {
+        self.length_table().length()
+    }
+}
+
+impl Transform for RationalBezier {
+    /// Transforms the control points and keeps the weights, since rational
+    /// bezier curves are invariant under affine transforms.
+    fn transform(&mut self, m: &Affine) {
+        for p in self.ctrl_point.iter_mut() {
+            p.transform(m);
+        }
+        self.table = OnceCell::new();
+    }
+}
+
+/// Appends the end parameter of each flat part of the curve with the
+/// weighted control points a and weights w, which spans t0 to t1 of the
+/// original curve.
+fn flatten_rational(
+    a: &[Point],
+    w: &[f64],
+    t0: f64,
+    t1: f64,
+    tolerance: f64,
+    depth: u32,
+    params: &mut Vec<f64>,
+) {
+    let p: Vec<Point> = a.iter().zip(w).map(|(a, w)| *a * (1.0 / w)).collect();
+    let (start, end) = (&p[0], &p[p.len() - 1]);
+    let flat = p[1..p.len() - 1]
+        .iter()
+        .all(|q| segment_distance(q, start, end) <= tolerance);
+    if flat || depth >= FLATTEN_MAX_DEPTH {
+        params.push(t1);
+    } else {
+        let (a_left, a_right) = split_ctrl_points(a, 0.5);
+        let (w_left, w_right) = split_ctrl_points(w, 0.5);
+        let mid = (t0 + t1) * 0.5;
+        flatten_rational(&a_left, &w_left, t0, mid, tolerance, depth + 1, params);
+        flatten_rational(&a_right, &w_right, mid, t1, tolerance, depth + 1, params);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::offset::Offset;
+
+    /// 18 inch sectional track radius in millimetres
+    const RADIUS: f64 = 457.2;
+
+    #[test]
+    fn test_arc() {
+        let center = Point { x: 10.0, y: 20.0 };
+        let arc = RationalBezier::arc(center, RADIUS, 0.25, PI / 6.0, 0.1).unwrap();
+        for i in 0..=20 {
+            let t = i as f64 / 20.0;
+            let p = arc.point_at(t);
+            assert!((p.distance(&center) - RADIUS).abs() < 1e-3);
+            assert!((arc.radius_at(t) - RADIUS).abs() < 0.05);
+            assert!(arc.curvature_at(t) > 0.0);
+        }
+        let end = arc.point_at(1.0);
+        let angle = (end.y - center.y).atan2(end.x - center.x);
+        assert!((angle - (0.25 + PI / 6.0)).abs() < 1e-5);
+        assert!((arc.length() - RADIUS * PI / 6.0).abs() < 1e-2);
+        let table = arc.arc_length_table(0.001);
+        assert!((table.length() - RADIUS * PI / 6.0).abs() < 1e-2);
+
+        let right = RationalBezier::arc(center, RADIUS, 0.25, -PI / 6.0, 0.1).unwrap();
+        assert!(right.curvature_at(0.5) < 0.0);
+        assert!((right.point_at(0.5).distance(&center) - RADIUS).abs() < 1e-3);
+
+        // the end tangents of a half circle never meet
+        assert!(RationalBezier::arc(center, RADIUS, 0.0, PI, 0.1).is_none());
+        assert!(RationalBezier::arc(center, RADIUS, 0.0, -4.0, 0.1).is_none());
+    }
+
+    #[test]
+    fn test_flatten() {
+        let arc =
+            RationalBezier::arc(Point { x: 0.0, y: 0.0 }, RADIUS, 0.0, PI / 4.0, 0.1).unwrap();
+        let params = arc.flatten();
+        assert_eq!(params[0], 0.0);
+        assert_eq!(*params.last().unwrap(), 1.0);
+        let points = arc.polyline();
+        for pair in points.windows(2) {
+            let mid = (pair[0] + pair[1]) * 0.5;
+            assert!(RADIUS - mid.magnitude() <= 0.1);
+        }
+    }
+
+    #[test]
+    fn test_offset() {
+        let center = Point { x: 0.0, y: 0.0 };
+        let arc = RationalBezier::arc(center, RADIUS, 0.0, PI / 8.0, 0.1).unwrap();
+        let rail = arc.offset_polyline(8.25, 0.1).unwrap();
+        for p in &rail {
+            assert!((p.distance(&center) - (RADIUS - 8.25)).abs() < 1e-2);
+        }
+        for curve in arc.offset_curves(-8.25, 0.1).unwrap() {
+            for i in 0..=10 {
+                let p = curve.point_at(i as f64 / 10.0);
+                assert!((p.distance(&center) - (RADIUS + 8.25)).abs() < 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut arc =
+            RationalBezier::arc(Point { x: 0.0, y: 0.0 }, RADIUS, 0.0, PI / 8.0, 0.1).unwrap();
+        arc.rotate(1.0, Point { x: 100.0, y: 0.0 });
+        arc.translate(-50.0, 20.0);
+        let center = Affine::rotate(1.0)
+            .about(Point { x: 100.0, y: 0.0 })
+            .then(&Affine::translate(-50.0, 20.0))
+            .apply(Point { x: 0.0, y: 0.0 });
+        for i in 0..=10 {
+            let p = arc.point_at(i as f64 / 10.0);
+            assert!((p.distance(&center) - RADIUS).abs() < 1e-2);
+        }
+        // the cached length follows a change of size
+        let length = arc.length();
+        arc.scale(2.0, center);
+        assert!((arc.length() - length * 2.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_closure() {
+        // 16 pieces of 22.5 degree sectional track make a full circle
+        let start = Point { x: 0.0, y: 0.0 };
+        let mut point = start;
+        let mut heading = Point { x: 1.0, y: 0.0 };
+        for _ in 0..16 {
+            let piece = RationalBezier::arc_from(point, heading, RADIUS, PI / 8.0, 0.1).unwrap();
+            point = piece.point_at(1.0);
+            heading = piece.tangent_at(1.0);
+        }
+        assert!(point.distance(&start) < 0.01);
+        assert!((heading.x - 1.0).abs() < 1e-5);
+    }
+}
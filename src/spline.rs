@@ -0,0 +1,208 @@
+use crate::{
+    bezier::{solve_linear, Bezier},
+    geometry::Point,
+    path::BezierPath,
+};
+
+impl BezierPath {
+    /// Creates a path of cubic curves that passes through the points with a
+    /// centripetal Catmull-Rom spline. Gets None if there are fewer than 2
+    /// distinct points.
+    ///
+    /// The tangent at each interior point depends only on its neighbours, so
+    /// moving a point changes the two segments either side of it. The
+    /// centripetal parameterization uses the square root of the distance
+    /// between the points as the knot spacing, which keeps the spline from
+    /// forming loops or cusps between closely spaced points.
+    ///
+    /// The start and end tangents are directions of travel and default to
+    /// the direction of the first and last chords.
+    pub fn catmull_rom(
+        points: &[Point],
+        start: Option<Point>,
+        end: Option<Point>,
+        tolerance: f64,
+    ) -> Option<BezierPath> {
+        let points = distinct(points)?;
+        let n = points.len() - 1;
+        let dt: Vec<f64> = points
+            .windows(2)
+            .map(|p| p[0].distance(&p[1]).sqrt())
+            .collect();
+        // tangents with respect to the knot parameter
+        let mut m = Vec::with_capacity(n + 1);
+        m.push(end_tangent(start, points[1] - points[0], dt[0]));
+        for i in 1..n {
+            let (a, b) = (dt[i - 1], dt[i]);
+            m.push(
+                (points[i] - points[i - 1]) * (1.0 / a)
+                    - (points[i + 1] - points[i - 1]) * (1.0 / (a + b))
+                    + (points[i + 1] - points[i]) * (1.0 / b),
+            );
+        }
+        m.push(end_tangent(end, points[n] - points[n - 1], dt[n - 1]));
+        Some(hermite_path(&points, &m, &dt, tolerance))
+    }
+
+    /// Creates a path of cubic curves that passes through the points with a
+    /// natural cubic spline parameterized by chord length. Gets None if
+    /// there are fewer than 2 distinct points.
+    ///
+    /// The curvature of the spline is continuous through every point, so
+    /// the joints of the path are G2 continuous, but moving one point
+    /// changes the whole spline. An end without a chosen tangent is free and
+    /// has zero curvature.
+    pub fn natural_spline(
+        points: &[Point],
+        start: Option<Point>,
+        end: Option<Point>,
+        tolerance: f64,
+    ) -> Option<BezierPath> {
+        let points = distinct(points)?;
+        let n = points.len() - 1;
+        let h: Vec<f64> = points.windows(2).map(|p| p[0].distance(&p[1])).collect();
+        let chord = |i: usize| (points[i + 1] - points[i]) * (1.0 / h[i]);
+
+        // matching the second derivatives either side of each interior point
+        // gives a tridiagonal system for the derivatives at the points
+        let mut matrix = vec![vec![0.0; n + 1]; n + 1];
+        let mut rhs = vec![Point { x: 0.0, y: 0.0 }; n + 1];
+        match start.and_then(|t| t.normalize()) {
+            Some(t) => {
+                matrix[0][0] = 1.0;
+                rhs[0] = t;
+            }
+            None => {
+                matrix[0][0] = 2.0;
+                matrix[0][1] = 1.0;
+                rhs[0] = chord(0) * 3.0;
+            }
+        }
+        for i in 1..n {
+            matrix[i][i - 1] = h[i];
+            matrix[i][i] = 2.0 * (h[i - 1] + h[i]);
+            matrix[i][i + 1] = h[i - 1];
+            rhs[i] = (chord(i - 1) * h[i] + chord(i) * h[i - 1]) * 3.0;
+        }
+        match end.and_then(|t| t.normalize()) {
+            Some(t) => {
+                matrix[n][n] = 1.0;
+                rhs[n] = t;
+            }
+            None => {
+                matrix[n][n - 1] = 1.0;
+                matrix[n][n] = 2.0;
+                rhs[n] = chord(n - 1) * 3.0;
+            }
+        }
+        let d = solve_linear(matrix, rhs)?;
+        Some(hermite_path(&points, &d, &h, tolerance))
+    }
+}
+
+/// Gets the points without consecutive duplicates, or None if fewer than 2
+/// remain.
+fn distinct(points: &[Point]) -> Option<Vec<Point>> {
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| a.distance(b) <= f64::EPSILON);
+    (points.len() >= 2).then_some(points)
+}
+
+/// Gets the tangent at an end of a Catmull-Rom spline whose end chord spans
+/// the knot interval dt. A chosen direction keeps the speed of the chord.
+fn end_tangent(direction: Option<Point>, chord: Point, dt: f64) -> Point {
+    let scale = 1.0 / dt;
+    match direction.and_then(|d| d.normalize()) {
+        Some(d) => d * (chord.magnitude() * scale),
+        None => chord * scale,
+    }
+}
+
+/// Creates the path of cubic Hermite segments through the points with the
+/// derivatives d at the points, where segment i spans the parameter
+/// interval h[i]. The handles are a third of the interval along the
+/// derivatives.
+fn hermite_path(points: &[Point], d: &[Point], h: &[f64], tolerance: f64) -> BezierPath {
+    let segment = |i: usize| {
+        let reach = h[i] / 3.0;
+        Bezier::new_with_ctrl_point(
+            [
+                points[i],
+                points[i] + d[i] * reach,
+                points[i + 1] - d[i + 1] * reach,
+                points[i + 1],
+            ],
+            tolerance,
+        )
+    };
+    let mut path = BezierPath::new(segment(0));
+    for i in 1..h.len() {
+        path.push(segment(i));
+    }
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{curve::Curve, path::Continuity};
+
+    fn waypoints() -> Vec<Point> {
+        vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 300.0, y: 50.0 },
+            Point { x: 500.0, y: 300.0 },
+            Point { x: 520.0, y: 320.0 },
+            Point { x: 800.0, y: 300.0 },
+        ]
+    }
+
+    #[test]
+    fn test_catmull_rom() {
+        let points = waypoints();
+        let path = BezierPath::catmull_rom(&points, None, None, 0.1).unwrap();
+        assert_eq!(path.segment_count(), 4);
+        for (i, s) in path.segments().iter().enumerate() {
+            assert_eq!(s.point_at(0.0), points[i]);
+            assert_eq!(s.point_at(1.0), points[i + 1]);
+        }
+        assert!(path.continuity() >= Continuity::G1);
+
+        let up = Point { x: 0.0, y: 1.0 };
+        let path = BezierPath::catmull_rom(&points, Some(up), Some(up), 0.1).unwrap();
+        assert!((path.tangent_at(0.0) - up).magnitude() < 1e-5);
+        assert!((path.tangent_at(1.0) - up).magnitude() < 1e-5);
+        assert!(path.continuity() >= Continuity::G1);
+    }
+
+    #[test]
+    fn test_natural_spline() {
+        let points = waypoints();
+        let path = BezierPath::natural_spline(&points, None, None, 0.1).unwrap();
+        for (i, s) in path.segments().iter().enumerate() {
+            assert_eq!(s.point_at(0.0), points[i]);
+            assert_eq!(s.point_at(1.0), points[i + 1]);
+        }
+        assert_eq!(path.continuity(), Continuity::G2);
+        // free ends have no curvature
+        assert!(path.curvature_at(0.0).abs() < 1e-6);
+        assert!(path.curvature_at(1.0).abs() < 1e-6);
+
+        let left = Point { x: -1.0, y: 1.0 };
+        let path = BezierPath::natural_spline(&points, None, Some(left), 0.1).unwrap();
+        assert!(path.curvature_at(0.0).abs() < 1e-6);
+        assert!((path.tangent_at(1.0) - left.normalize().unwrap()).magnitude() < 1e-5);
+        assert_eq!(path.continuity(), Continuity::G2);
+    }
+
+    #[test]
+    fn test_spline_degenerate() {
+        let p = Point { x: 1.0, y: 1.0 };
+        assert!(BezierPath::catmull_rom(&[p, p], None, None, 0.1).is_none());
+        assert!(BezierPath::natural_spline(&[p], None, None, 0.1).is_none());
+        let q = Point { x: 4.0, y: 5.0 };
+        let path = BezierPath::natural_spline(&[p, p, q], None, None, 0.1).unwrap();
+        assert_eq!(path.segment_count(), 1);
+        assert!((path.length() - 5.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,466 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::{
+    bezier::Bezier,
+    biarc::ArcSegment,
+    clothoid::Clothoid,
+    curve::Curve,
+    geometry::{Arc, Line, Point, Rect},
+};
+
+/// The geometry of a piece of track measured by the distance along it.
+///
+/// Straight and curved sectional track, flex track and easements all
+/// implement it, so the layout can measure, draw and cut any piece of track
+/// the same way. Distances outside of the curve are clamped to its ends.
+///
+/// The methods are named apart from those of `Curve`, which are measured by
+/// the curve parameter, so that both traits can be used on the same curve.
+pub trait TrackCurve {
+    /// Gets the length of the curve
+    fn track_length(&self) -> f64;
+
+    /// Gets the point at the distance along the curve
+    fn point_along(&self, distance: f64) -> Point;
+
+    /// Gets the unit tangent in the direction of travel at the distance
+    /// along the curve
+    fn tangent_along(&self, distance: f64) -> Point;
+
+    /// Gets the signed curvature at the distance along the curve, positive
+    /// where the curve turns counter-clockwise
+    fn curvature_along(&self, distance: f64) -> f64;
+
+    /// Gets the axis aligned bounding box of the curve
+    fn track_bounds(&self) -> Rect;
+
+    /// Gets the vertices of a polyline that stays within tolerance of the
+    /// curve, from the start to the end.
+    fn polyline_within(&self, tolerance: f64) -> Vec<Point>;
+
+    /// Splits the curve at the distance along it into the parts before and
+    /// after that point.
+    fn split_along(&self, distance: f64) -> (Self, Self)
+    where
+        Self: Sized;
+
+    /// Gets the curve traversed from its end to its start
+    fn reverse(&self) -> Self
+    where
+        Self: Sized;
+}
+
+impl TrackCurve for Line {
+    fn track_length(&self) -> f64 {
+        self.length()
+    }
+
+    fn point_along(&self, distance: f64) -> Point {
+        self.point_at(fraction(distance, self.length()))
+    }
+
+    fn tangent_along(&self, _distance: f64) -> Point {
+        (self.end - self.start)
+            .normalize()
+            .unwrap_or(Point { x: 1.0, y: 0.0 })
+    }
+
+    fn curvature_along(&self, _distance: f64) -> f64 {
+        0.0
+    }
+
+    fn track_bounds(&self) -> Rect {
+        Rect::from_points([self.start, self.end].iter()).unwrap()
+    }
+
+    fn polyline_within(&self, _tolerance: f64) -> Vec<Point> {
+        vec![self.start, self.end]
+    }
+
+    fn split_along(&self, distance: f64) -> (Line, Line) {
+        let p = self.point_along(distance);
+        (Line::new(self.start, p), Line::new(p, self.end))
+    }
+
+    fn reverse(&self) -> Line {
+        Line::new(self.end, self.start)
+    }
+}
+
+impl TrackCurve for Arc {
+    fn track_length(&self) -> f64 {
+        self.length()
+    }
+
+    fn point_along(&self, distance: f64) -> Point {
+        self.point_at(fraction(distance, self.length()))
+    }
+
+    fn tangent_along(&self, distance: f64) -> Point {
+        self.tangent_at(fraction(distance, self.length()))
+    }
+
+    fn curvature_along(&self, _distance: f64) -> f64 {
+        self.sweep.signum() / self.radius
+    }
+
+    /// Gets the bounds of the end points and of the points where the arc
+    /// crosses the horizontal and vertical through its center.
+    fn track_bounds(&self) -> Rect {
+        let mut points = vec![self.start(), self.end()];
+        for quadrant in -4..=4 {
+            let angle = quadrant as f64 * FRAC_PI_2;
+            if self.contains_angle(angle) {
+                points.push(
+                    self.center
+                        + Point {
+                            x: angle.cos(),
+                            y: angle.sin(),
+                        } * self.radius,
+                );
+            }
+        }
+        Rect::from_points(points.iter()).unwrap()
+    }
+
+    /// Gets evenly spaced points whose chords have a sagitta of at most the
+    /// tolerance, using the chord length sqrt(8 * r * tolerance).
+    fn polyline_within(&self, tolerance: f64) -> Vec<Point> {
+        let chord = (8.0 * self.radius * tolerance).sqrt();
+        let count = (self.length() / chord).ceil().max(1.0) as usize;
+        (0..=count)
+            .map(|i| self.point_at(i as f64 / count as f64))
+            .collect()
+    }
+
+    fn split_along(&self, distance: f64) -> (Arc, Arc) {
+        let sweep = self.sweep * fraction(distance, self.length());
+        (
+            Arc::new(self.center, self.radius, self.start_angle, sweep),
+            Arc::new(
+                self.center,
+                self.radius,
+                self.start_angle + sweep,
+                self.sweep - sweep,
+            ),
+        )
+    }
+
+    fn reverse(&self) -> Arc {
+        Arc::new(
+            self.center,
+            self.radius,
+            self.start_angle + self.sweep,
+            -self.sweep,
+        )
+    }
+}
+
+impl TrackCurve for ArcSegment {
+    fn track_length(&self) -> f64 {
+        self.length()
+    }
+
+    fn point_along(&self, distance: f64) -> Point {
+        match self {
+            ArcSegment::Line(line) => line.point_along(distance),
+            ArcSegment::Arc(arc) => arc.point_along(distance),
+        }
+    }
+
+    fn tangent_along(&self, distance: f64) -> Point {
+        match self {
+            ArcSegment::Line(line) => line.tangent_along(distance),
+            ArcSegment::Arc(arc) => arc.tangent_along(distance),
+        }
+    }
+
+    fn curvature_along(&self, distance: f64) -> f64 {
+        match self {
+            ArcSegment::Line(line) => line.curvature_along(distance),
+            ArcSegment::Arc(arc) => arc.curvature_along(distance),
+        }
+    }
+
+    fn track_bounds(&self) -> Rect {
+        match self {
+            ArcSegment::Line(line) => line.track_bounds(),
+            ArcSegment::Arc(arc) => arc.track_bounds(),
+        }
+    }
+
+    fn polyline_within(&self, tolerance: f64) -> Vec<Point> {
+        match self {
+            ArcSegment::Line(line) => line.polyline_within(tolerance),
+            ArcSegment::Arc(arc) => arc.polyline_within(tolerance),
+        }
+    }
+
+    fn split_along(&self, distance: f64) -> (ArcSegment, ArcSegment) {
+        match self {
+            ArcSegment::Line(line) => {
+                let (a, b) = line.split_along(distance);
+                (ArcSegment::Line(a), ArcSegment::Line(b))
+            }
+            ArcSegment::Arc(arc) => {
+                let (a, b) = arc.split_along(distance);
+                (ArcSegment::Arc(a), ArcSegment::Arc(b))
+            }
+        }
+    }
+
+    fn reverse(&self) -> ArcSegment {
+        match self {
+            ArcSegment::Line(line) => ArcSegment::Line(line.reverse()),
+            ArcSegment::Arc(arc) => ArcSegment::Arc(arc.reverse()),
+        }
+    }
+}
+
+impl TrackCurve for Bezier {
+    fn track_length(&self) -> f64 {
+        self.length()
+    }
+
+    fn point_along(&self, distance: f64) -> Point {
+        self.point_at(self.t_at_distance(distance))
+    }
+
+    fn tangent_along(&self, distance: f64) -> Point {
+        self.tangent_at(self.t_at_distance(distance))
+    }
+
+    fn curvature_along(&self, distance: f64) -> f64 {
+        self.curvature_at(self.t_at_distance(distance))
+    }
+
+    fn track_bounds(&self) -> Rect {
+        self.bounds()
+    }
+
+    /// Gets the cached polyline of the curve when it is already within the
+    /// tolerance, and only flattens the curve again for a finer tolerance
+    fn polyline_within(&self, tolerance: f64) -> Vec<Point> {
+        if tolerance >= self.tolerance() {
+            return self.polyline();
+        }
+        let mut curve = self.clone();
+        curve.set_tolerance(tolerance);
+        curve.polyline()
+    }
+
+    fn split_along(&self, distance: f64) -> (Bezier, Bezier) {
+        self.split(self.t_at_distance(distance))
+    }
+
+    fn reverse(&self) -> Bezier {
+        self.subcurve(1.0, 0.0)
+    }
+}
+
+impl Bezier {
+    /// Gets the parameter at the distance along the curve from the cached
+    /// arc length table
+    fn t_at_distance(&self, distance: f64) -> f64 {
+        self.length_table().t_at_distance(distance)
+    }
+}
+
+impl TrackCurve for Clothoid {
+    fn track_length(&self) -> f64 {
+        self.length()
+    }
+
+    fn point_along(&self, distance: f64) -> Point {
+        self.point_at(fraction(distance, self.length()))
+    }
+
+    fn tangent_along(&self, distance: f64) -> Point {
+        let heading = self.heading_at(fraction(distance, self.length()) * self.length());
+        Point {
+            x: heading.cos(),
+            y: heading.sin(),
+        }
+    }
+
+    fn curvature_along(&self, distance: f64) -> f64 {
+        self.start_curvature()
+            + self.curvature_rate() * fraction(distance, self.length()) * self.length()
+    }
+
+    /// Gets the bounds of the flattened curve grown by its tolerance
+    fn track_bounds(&self) -> Rect {
+        Rect::from_points(self.polyline().iter())
+            .unwrap()
+            .inflate(self.tolerance())
+    }
+
+    fn polyline_within(&self, tolerance: f64) -> Vec<Point> {
+        let curve = Clothoid::new(
+            self.start(),
+            self.heading(),
+            self.start_curvature(),
+            self.end_curvature(),
+            self.length(),
+            tolerance,
+        );
+        curve.polyline()
+    }
+
+    fn split_along(&self, distance: f64) -> (Clothoid, Clothoid) {
+        let length = self.length();
+        let s = fraction(distance, self.length()) * length;
+        let k = self.curvature_along(s);
+        (
+            Clothoid::new(
+                self.start(),
+                self.heading(),
+                self.start_curvature(),
+                k,
+                s,
+                self.tolerance(),
+            ),
+            Clothoid::new(
+                self.point_along(s),
+                self.heading_at(s),
+                k,
+                self.end_curvature(),
+                length - s,
+                self.tolerance(),
+            ),
+        )
+    }
+
+    /// Gets the spiral from the end, where travelling the other way turns
+    /// the heading around and changes the sign of the curvatures.
+    fn reverse(&self) -> Clothoid {
+        let length = self.length();
+        Clothoid::new(
+            self.point_at(1.0),
+            self.heading_at(length) + PI,
+            -self.end_curvature(),
+            -self.start_curvature(),
+            length,
+            self.tolerance(),
+        )
+    }
+}
+
+/// Gets the fraction of the length of a curve at the distance along it,
+/// clamped to the ends of the curve
+fn fraction(distance: f64, length: f64) -> f64 {
+    if length <= 0.0 {
+        return 0.0;
+    }
+    (distance / length).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let line = Line::new(Point { x: 0.0, y: 0.0 }, Point { x: 30.0, y: 40.0 });
+        assert_eq!(line.track_length(), 50.0);
+        assert_eq!(line.point_along(25.0), Point { x: 15.0, y: 20.0 });
+        assert_eq!(line.point_along(100.0), line.end);
+        assert_eq!(line.curvature_along(10.0), 0.0);
+        let (a, b) = line.split_along(10.0);
+        assert_eq!(a.end, b.start);
+        assert!((a.track_length() - 10.0).abs() < 1e-5);
+        assert_eq!(
+            line.reverse().tangent_along(0.0),
+            Point { x: -0.6, y: -0.8 }
+        );
+    }
+
+    #[test]
+    fn test_arc() {
+        // a 30 degree piece of 18 inch radius sectional track
+        let radius = 457.2;
+        let arc = Arc::new(Point { x: 0.0, y: 0.0 }, radius, -FRAC_PI_2, PI / 6.0);
+        let length = radius * PI / 6.0;
+        assert!((arc.track_length() - length).abs() < 1e-3);
+        assert_eq!(arc.curvature_along(0.0), 1.0 / radius);
+        assert_eq!(arc.point_along(0.0), arc.start());
+        assert!((arc.tangent_along(0.0) - Point { x: 1.0, y: 0.0 }).magnitude() < 1e-6);
+
+        let (a, b) = arc.split_along(length * 0.5);
+        assert!((a.sweep - PI / 12.0).abs() < 1e-6);
+        assert!(a.end().distance(&b.start()) < 1e-3);
+        let reversed = arc.reverse();
+        assert!(reversed.start().distance(&arc.end()) < 1e-3);
+        assert!(reversed.curvature_along(0.0) < 0.0);
+
+        for p in arc.polyline_within(0.1) {
+            assert!((p.magnitude() - radius).abs() < 1e-3);
+        }
+        // a half circle reaches the x axis extreme
+        let half = Arc::new(Point { x: 0.0, y: 0.0 }, 10.0, -FRAC_PI_2, PI);
+        let r = half.track_bounds();
+        assert!((r.x + r.width - 10.0).abs() < 1e-4);
+        assert!(r.x.abs() < 1e-4);
+        assert!((r.height - 20.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_bezier() {
+        let b = Bezier::new_with_ctrl_point(
+            [
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 100.0, y: 0.0 },
+                Point { x: 200.0, y: 100.0 },
+                Point { x: 300.0, y: 100.0 },
+            ],
+            0.01,
+        );
+        let length = b.track_length();
+        // the length and the distance lookups come from the same table
+        assert_eq!(b.point_along(length), b.point_at(1.0));
+        let mut moved = b.clone();
+        moved.set_control_point(3, Point { x: 400.0, y: 100.0 });
+        assert!(moved.track_length() > length + 50.0);
+
+        let (first, second) = b.split_along(length * 0.25);
+        assert!((first.track_length() - length * 0.25).abs() < 0.05);
+        assert!((second.track_length() - length * 0.75).abs() < 0.05);
+        let reversed = b.reverse();
+        assert_eq!(reversed.point_at(0.0), b.point_at(1.0));
+        let p = reversed.point_along(length * 0.75);
+        assert!(p.distance(&b.point_along(length * 0.25)) < 0.05);
+        assert!((reversed.curvature_along(10.0) + b.curvature_along(length - 10.0)).abs() < 1e-4);
+
+        // a looser tolerance reuses the cached polyline and a finer one does not
+        assert_eq!(b.polyline_within(0.25), b.curve());
+        assert!(b.polyline_within(0.001).len() > b.curve().len());
+    }
+
+    #[test]
+    fn test_clothoid() {
+        let spiral = Clothoid::new(Point { x: 0.0, y: 0.0 }, 0.0, 0.0, 0.01, 200.0, 0.1);
+        assert!((spiral.curvature_along(50.0) - 0.0025).abs() < 1e-7);
+        let tangent = spiral.tangent_along(200.0);
+        assert!(
+            (tangent
+                - Point {
+                    x: 1.0_f64.cos(),
+                    y: 1.0_f64.sin()
+                })
+            .magnitude()
+                < 1e-5
+        );
+
+        let (a, b) = spiral.split_along(80.0);
+        assert!((a.track_length() - 80.0).abs() < 1e-4);
+        assert!(a.point_at(1.0).distance(&b.point_at(0.0)) < 1e-3);
+        assert!(b.point_at(1.0).distance(&spiral.point_at(1.0)) < 1e-3);
+        assert!((a.end_curvature() - b.start_curvature()).abs() < 1e-7);
+
+        let reversed = spiral.reverse();
+        assert!(reversed.point_at(1.0).distance(&spiral.point_at(0.0)) < 1e-3);
+        assert!((reversed.curvature_along(0.0) + 0.01).abs() < 1e-7);
+        let r = spiral.track_bounds();
+        assert!(r.contains(spiral.point_at(0.5)));
+    }
+}
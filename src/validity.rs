@@ -0,0 +1,305 @@
+use crate::{bezier::Bezier, curve::Curve, intersect::polynomial_roots, path::BezierPath};
+
+/// A cubic whose parameters of self-intersection are closer than this has a
+/// cusp rather than a loop.
+const CUSP_EPSILON: f64 = 1e-4;
+/// Vectors with a smaller sine of the angle between them are parallel
+const PARALLEL_EPSILON: f64 = 1e-6;
+/// Control points closer than this in layout units to the chord of the
+/// curve are on a straight line.
+const STRAIGHT_EPSILON: f64 = 1e-3;
+/// Curvatures smaller than this are straight
+const STRAIGHT_CURVATURE: f64 = 1e-6;
+
+/// The features of a curve that real flex track cannot follow
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeAnalysis {
+    /// Parameters where the curve changes from turning one way to the other
+    pub inflections: Vec<f64>,
+    /// Parameters where the curve stops and turns back on itself
+    pub cusps: Vec<f64>,
+    /// The two parameters where the curve crosses itself
+    pub self_intersection: Option<(f64, f64)>,
+}
+
+/// Whether a shape can be built with flex track, in decreasing order of
+/// severity.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Validity {
+    /// The curve crosses itself at the two parameters
+    Loop(f64, f64),
+    /// The curve turns back on itself at the parameters
+    Cusp(Vec<f64>),
+    /// The curve reverses direction at the parameters without a straight
+    /// section in between, which throws couplers out of line
+    ReverseCurve(Vec<f64>),
+    /// The shape can be built
+    Valid,
+}
+
+impl Bezier {
+    /// Finds the inflections, cusps and self-intersection of the curve, or
+    /// None if the degree of the curve is greater than 3. Lines and
+    /// quadratics are analyzed as the cubic of the same shape.
+    ///
+    /// With the cubic written in the power basis
+    ///      C(t) = a * t^3 + b * t^2 + c * t + d
+    /// the inflections are the roots of C'(t) x C''(t), which reduces to
+    ///      3 * (a x b) * t^2 + 3 * (a x c) * t + (b x c) = 0
+    /// The curve meets itself where C(s) = C(t) for s != t. Dividing by
+    /// s - t gives
+    ///      a * (σ^2 - π) + b * σ + c = 0
+    /// with σ = s + t and π = s * t, which is solved for σ with the cross
+    /// product with a, and then for π with the dot product with a. The
+    /// curve has a loop when s and t are distinct and real, and a cusp when
+    /// they are equal.
+    pub fn analyze(&self) -> Option<ShapeAnalysis> {
+        let mut cubic = self.clone();
+        while cubic.degree() < 3 {
+            cubic = cubic.elevate_degree();
+        }
+        if cubic.degree() > 3 {
+            return None;
+        }
+        let p = cubic.control_points();
+        let a = p[3] - p[0] + (p[1] - p[2]) * 3.0;
+        let b = (p[0] - p[1] * 2.0 + p[2]) * 3.0;
+        let c = (p[1] - p[0]) * 3.0;
+        let interior = |t: &f64| *t > 0.0 && *t < 1.0;
+
+        let mut inflections =
+            polynomial_roots(&[b.cross(&c), 3.0 * a.cross(&c), 3.0 * a.cross(&b)], 0.0).0;
+        inflections.retain(interior);
+        let mut analysis = ShapeAnalysis {
+            inflections,
+            cusps: Vec::new(),
+            self_intersection: None,
+        };
+
+        if is_straight(&cubic) {
+            // a straight curve has a cusp wherever it reverses along the line
+            if let Some(u) = (p[3] - p[0]).normalize().or((p[1] - p[0]).normalize()) {
+                let mut cusps =
+                    polynomial_roots(&[c.dot(&u), 2.0 * b.dot(&u), 3.0 * a.dot(&u)], 0.0).0;
+                cusps.retain(interior);
+                analysis.cusps = cusps;
+            }
+            analysis.inflections.clear();
+            return Some(analysis);
+        }
+
+        let axb = a.cross(&b);
+        if axb.abs() <= PARALLEL_EPSILON * a.magnitude() * b.magnitude() {
+            return Some(analysis);
+        }
+        let sigma = -a.cross(&c) / axb;
+        let pi = sigma * sigma + (sigma * b.dot(&a) + c.dot(&a)) / a.dot(&a);
+        let discriminant = sigma * sigma - 4.0 * pi;
+        if discriminant.abs() <= CUSP_EPSILON {
+            let t = sigma * 0.5;
+            if interior(&t) {
+                analysis.cusps.push(t);
+            }
+        } else if discriminant > 0.0 {
+            let root = discriminant.sqrt();
+            let (s, t) = ((sigma - root) * 0.5, (sigma + root) * 0.5);
+            if (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t) {
+                analysis.self_intersection = Some((s, t));
+            }
+        }
+        Some(analysis)
+    }
+
+    /// Classifies whether the curve can be built with flex track, or None if
+    /// the degree of the curve is greater than 3. A single curve with an
+    /// inflection is an S-curve with no straight section between its two
+    /// halves.
+    pub fn validity(&self) -> Option<Validity> {
+        let analysis = self.analyze()?;
+        Some(if let Some((s, t)) = analysis.self_intersection {
+            Validity::Loop(s, t)
+        } else if !analysis.cusps.is_empty() {
+            Validity::Cusp(analysis.cusps)
+        } else if !analysis.inflections.is_empty() {
+            Validity::ReverseCurve(analysis.inflections)
+        } else {
+            Validity::Valid
+        })
+    }
+
+    /// Checks if flex track can follow the curve, which it cannot if the curve
+    /// loops or has a cusp
+    pub(crate) fn is_buildable(&self) -> bool {
+        !matches!(
+            self.validity(),
+            Some(Validity::Loop(..)) | Some(Validity::Cusp(_))
+        )
+    }
+}
+
+impl BezierPath {
+    /// Gets the path parameters where the path reverses direction without a
+    /// straight section of at least min_straight layout units in between.
+    ///
+    /// Each inflection within a segment is a reversal, as is a joint where
+    /// the segment before turns one way and the segment after the other. Any
+    /// straight segments between curves count towards the straight section.
+    pub fn reverse_curves(&self, min_straight: f64) -> Vec<f64> {
+        let n = self.segment_count() as f64;
+        let mut reversals = Vec::new();
+        let mut hand = 0.0;
+        let mut straight = 0.0;
+        for (i, segment) in self.segments().iter().enumerate() {
+            if is_straight(segment) {
+                straight += segment.length();
+                continue;
+            }
+            let inflections = segment.analyze().map(|a| a.inflections).unwrap_or_default();
+            let (start, end) = end_hands(segment, &inflections);
+            if start != 0.0 && hand != 0.0 && start != hand && straight < min_straight {
+                reversals.push(i as f64 / n);
+            }
+            reversals.extend(inflections.iter().map(|t| (i as f64 + t) / n));
+            if end != 0.0 {
+                hand = end;
+            }
+            straight = 0.0;
+        }
+        reversals
+    }
+}
+
+/// Checks if all of the control points are on one straight line, which is
+/// measured from the first control point towards the one furthest from it.
+fn is_straight(curve: &Bezier) -> bool {
+    let p = curve.control_points();
+    let furthest = p
+        .iter()
+        .max_by(|a, b| a.distance(&p[0]).total_cmp(&b.distance(&p[0])))
+        .unwrap();
+    let Some(u) = (*furthest - p[0]).normalize() else {
+        return true;
+    };
+    p.iter()
+        .all(|q| (*q - p[0]).cross(&u).abs() <= STRAIGHT_EPSILON)
+}
+
+/// Gets the direction the curve turns at its start and end, 1.0 for
+/// counter-clockwise, -1.0 for clockwise or 0.0 for straight. Where the
+/// curvature vanishes at an end the direction of the curve up to the
+/// nearest inflection is used.
+fn end_hands(curve: &Bezier, inflections: &[f64]) -> (f64, f64) {
+    let hand = |t: f64| {
+        let k = curve.curvature_at(t);
+        if k.abs() <= STRAIGHT_CURVATURE {
+            0.0
+        } else {
+            k.signum()
+        }
+    };
+    let first = inflections.first().copied().unwrap_or(1.0);
+    let last = inflections.last().copied().unwrap_or(0.0);
+    let start = match hand(0.0) {
+        0.0 => hand(first * 0.5),
+        h => h,
+    };
+    let end = match hand(1.0) {
+        0.0 => hand((last + 1.0) * 0.5),
+        h => h,
+    };
+    (start, end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        fixtures::{arch, bezier},
+        geometry::{Point, Transform},
+    };
+
+    #[test]
+    fn test_arch() {
+        let b = arch(0.1);
+        let analysis = b.analyze().unwrap();
+        assert!(analysis.inflections.is_empty());
+        assert!(analysis.cusps.is_empty());
+        assert!(analysis.self_intersection.is_none());
+        assert_eq!(b.validity(), Some(Validity::Valid));
+    }
+
+    #[test]
+    fn test_inflection() {
+        let b = bezier(
+            [(0.0, 0.0), (100.0, 0.0), (0.0, 100.0), (100.0, 100.0)],
+            0.1,
+        );
+        let analysis = b.analyze().unwrap();
+        assert_eq!(analysis.inflections.len(), 1);
+        assert!((analysis.inflections[0] - 0.5).abs() < 1e-5);
+        assert!(b.curvature_at(0.4) * b.curvature_at(0.6) < 0.0);
+        assert!(matches!(b.validity(), Some(Validity::ReverseCurve(_))));
+    }
+
+    #[test]
+    fn test_cusp() {
+        let b = bezier(
+            [(0.0, 0.0), (100.0, 100.0), (0.0, 100.0), (100.0, 0.0)],
+            0.1,
+        );
+        let analysis = b.analyze().unwrap();
+        assert_eq!(analysis.cusps.len(), 1);
+        assert!((analysis.cusps[0] - 0.5).abs() < 1e-3);
+        assert!(b.derivative_at(analysis.cusps[0]).magnitude() < 1e-2);
+        assert!(matches!(b.validity(), Some(Validity::Cusp(_))));
+
+        // a straight curve that backtracks along itself
+        let b = bezier([(0.0, 0.0), (100.0, 0.0), (-50.0, 0.0), (50.0, 0.0)], 0.1);
+        assert_eq!(b.analyze().unwrap().cusps.len(), 2);
+        let line = bezier([(0.0, 0.0), (10.0, 0.0), (20.0, 0.0), (30.0, 0.0)], 0.1);
+        assert_eq!(line.validity(), Some(Validity::Valid));
+    }
+
+    #[test]
+    fn test_loop() {
+        let b = bezier(
+            [(0.0, 0.0), (150.0, 100.0), (-50.0, 100.0), (100.0, 0.0)],
+            0.1,
+        );
+        let (s, t) = b.analyze().unwrap().self_intersection.unwrap();
+        assert!(s < t);
+        assert!(b.point_at(s).distance(&b.point_at(t)) < 1e-2);
+        assert_eq!(b.validity(), Some(Validity::Loop(s, t)));
+    }
+
+    #[test]
+    fn test_reverse_curves() {
+        let left = bezier([(0.0, 0.0), (50.0, 0.0), (100.0, 20.0), (100.0, 60.0)], 0.1);
+        let right = bezier(
+            [
+                (100.0, 60.0),
+                (100.0, 100.0),
+                (120.0, 140.0),
+                (160.0, 150.0),
+            ],
+            0.1,
+        );
+        let mut path = BezierPath::new(left.clone());
+        path.push(right.clone());
+        assert!(path.segments()[0].curvature_at(1.0) > 0.0);
+        assert!(path.segments()[1].curvature_at(0.0) < 0.0);
+        assert_eq!(path.reverse_curves(40.0), vec![0.5]);
+
+        // a 50 unit straight between the curves
+        let mut path = BezierPath::new(left);
+        path.push(Bezier::new_with_ctrl_point(
+            [Point { x: 100.0, y: 60.0 }, Point { x: 100.0, y: 110.0 }],
+            0.1,
+        ));
+        let mut right = right;
+        right.translate(0.0, 50.0);
+        path.push(right);
+        assert!(path.reverse_curves(40.0).is_empty());
+        assert_eq!(path.reverse_curves(60.0).len(), 1);
+    }
+}